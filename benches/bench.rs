@@ -1,5 +1,3 @@
-use std::ptr::copy_nonoverlapping;
-
 use tmmt::hash_mine::HashMine;
 use tmmt::two_ptr_mine::TwoPtrMine;
 
@@ -11,12 +9,12 @@ use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criteri
 type Block = u128;
 
 const fn generate_initialization_blocks<const SIZE: usize>() -> [Block; SIZE] {
-    let initial_blocks = [0; SIZE];
+    let mut initial_blocks = [0; SIZE];
 
-    unsafe {
-        let arr_ptr = &initial_blocks as *const [Block];
-        let arr_ptr_mut = arr_ptr as *mut [Block] as *mut Block;
-        copy_nonoverlapping(BLOCKS_100.as_ptr(), arr_ptr_mut, SIZE)
+    let mut i = 0;
+    while i < SIZE {
+        initial_blocks[i] = BLOCKS_100[i];
+        i += 1;
     }
 
     initial_blocks
@@ -29,17 +27,17 @@ const fn generate_input_blocks<const INITIAL_BLOCKS_SIZE: usize, const INPUT_BLO
     let mut input_blocks = [0 as Block; INPUT_BLOCKS_SIZE];
 
     // copy the initial blocks to the start of input blocks
-    unsafe {
-        let arr_ptr = &input_blocks as *const [Block];
-        let arr_ptr_mut = arr_ptr as *mut [Block] as *mut Block;
-        copy_nonoverlapping(initial_blocks.as_ptr(), arr_ptr_mut, INITIAL_BLOCKS_SIZE)
+    let mut i = 0;
+    while i < INITIAL_BLOCKS_SIZE {
+        input_blocks[i] = initial_blocks[i];
+        i += 1;
     }
 
     // a window for generating new items from old consecutive items
     let validation_window_size = INITIAL_BLOCKS_SIZE;
 
     // first free index in the input blocks
-    let mut i = validation_window_size;
+    i = validation_window_size;
     // create the rest of the Blocks
     while i < INPUT_BLOCKS_SIZE {
         input_blocks[i] =
@@ -0,0 +1,355 @@
+use crate::{
+    hash_mine::HashMine,
+    mine::{Block, Mine, MineError, RUNTIME_WINDOW_SIZE},
+    two_ptr_mine::TwoPtrMine,
+};
+
+use std::{collections::VecDeque, hash::Hash, mem, ops::Add};
+
+/// Largest window for which [HashMine] is considered. Past this point the
+/// O(VALIDATION_WINDOW_SIZE<sup>2</sup>) sum set outgrows any gain in validation speed.
+const HASH_MAX_WINDOW_SIZE: usize = 32;
+
+/// Largest block, in bytes, for which [HashMine] is considered. Hashing wider
+/// blocks costs more than the comparisons done by [TwoPtrMine].
+const HASH_MAX_BLOCK_SIZE: usize = mem::size_of::<u128>();
+
+/// Implementation backing an [AdaptiveMine].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Backed by [HashMine].
+    Hash,
+    /// Backed by [TwoPtrMine].
+    TwoPtr,
+}
+
+impl Strategy {
+    /// Pick the strategy expected to validate a stream fastest.
+    ///
+    /// [TwoPtrMine] is the default. [HashMine] is only picked when the window and
+    /// the blocks are small, and `expected_blocks` is known to be long enough to amortize
+    /// the O(VALIDATION_WINDOW_SIZE<sup>2</sup>) initialization.
+    pub fn choose<const VALIDATION_WINDOW_SIZE: usize, B>(expected_blocks: Option<usize>) -> Self {
+        Self::choose_for::<B>(VALIDATION_WINDOW_SIZE, expected_blocks)
+    }
+
+    /// Same as [Strategy::choose] for a `window_size` picked at runtime.
+    pub fn choose_for<B>(window_size: usize, expected_blocks: Option<usize>) -> Self {
+        let small_window = window_size <= HASH_MAX_WINDOW_SIZE;
+        let small_block = mem::size_of::<B>() <= HASH_MAX_BLOCK_SIZE;
+        let long_stream = expected_blocks
+            .is_some_and(|expected_blocks| expected_blocks >= window_size.saturating_pow(2));
+
+        if small_window && small_block && long_stream {
+            Strategy::Hash
+        } else {
+            Strategy::TwoPtr
+        }
+    }
+}
+
+/// Concrete implementation of [Mine] that picks the backing implementation
+/// at construction. See [Strategy::choose].
+///
+/// The mine can [migrate](AdaptiveMine::migrate) to a different strategy at any point
+/// without losing any of the validated state.
+#[derive(Clone, Debug)]
+pub enum AdaptiveMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone + Ord> {
+    Hash(HashMine<VALIDATION_WINDOW_SIZE, B>),
+    TwoPtr(TwoPtrMine<VALIDATION_WINDOW_SIZE, B>),
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> AdaptiveMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    /// Create a new mine with given `initialization_blocks`, expecting
+    /// roughly `expected_blocks` blocks to be validated.
+    /// No validation is performed on the initialization blocks.
+    pub fn with_hint(
+        initialization_blocks: [B; VALIDATION_WINDOW_SIZE],
+        expected_blocks: usize,
+    ) -> Self {
+        let strategy = Strategy::choose::<VALIDATION_WINDOW_SIZE, B>(Some(expected_blocks));
+        Self::with_strategy(initialization_blocks, strategy)
+    }
+
+    /// Create a new mine with given `initialization_blocks` backed by the given `strategy`.
+    /// No validation is performed on the initialization blocks.
+    pub fn with_strategy(
+        initialization_blocks: [B; VALIDATION_WINDOW_SIZE],
+        strategy: Strategy,
+    ) -> Self {
+        Self::from_window(
+            VecDeque::from(initialization_blocks),
+            VALIDATION_WINDOW_SIZE as u64,
+            strategy,
+        )
+    }
+
+    /// Create a mine from a full window of `validation_blocks`, given in arrival order,
+    /// after `total_blocks` blocks have been added to the mine.
+    /// No validation is performed on the window.
+    pub(crate) fn from_window(
+        validation_blocks: VecDeque<B>,
+        total_blocks: u64,
+        strategy: Strategy,
+    ) -> Self {
+        match strategy {
+            Strategy::Hash => Self::Hash(HashMine::from_window(validation_blocks, total_blocks)),
+            Strategy::TwoPtr => {
+                Self::TwoPtr(TwoPtrMine::from_window(validation_blocks, total_blocks))
+            }
+        }
+    }
+
+    /// Strategy currently backing the mine.
+    pub fn strategy(&self) -> Strategy {
+        match self {
+            Self::Hash(_) => Strategy::Hash,
+            Self::TwoPtr(_) => Strategy::TwoPtr,
+        }
+    }
+
+    /// Rebuild the mine with a different backing `strategy`.
+    /// The validation window and the block count are carried over, so validation
+    /// continues exactly where it left off. Migrating to the current strategy is a no-op.
+    /// # Performance
+    /// Costs as much as creating a new mine with the target strategy.
+    pub fn migrate(&mut self, strategy: Strategy) {
+        if self.strategy() == strategy {
+            return;
+        }
+
+        let (validation_blocks, total_blocks) = match self {
+            Self::Hash(mine) => (mine.validation_blocks.clone(), mine.total_blocks),
+            Self::TwoPtr(mine) => (mine.validation_blocks.clone(), mine.total_blocks),
+        };
+
+        *self = Self::from_window(validation_blocks, total_blocks, strategy);
+    }
+}
+
+impl<B> AdaptiveMine<RUNTIME_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    /// Create a new mine with a validation window sized at runtime, holding the
+    /// `initialization_blocks`, backed by the given `strategy`. Pick the strategy for
+    /// the window size with [Strategy::choose_for].
+    /// No validation is performed on the initialization blocks.
+    pub fn with_window(initialization_blocks: Vec<B>, strategy: Strategy) -> Self {
+        let total_blocks = initialization_blocks.len() as u64;
+        Self::from_window(
+            VecDeque::from(initialization_blocks),
+            total_blocks,
+            strategy,
+        )
+    }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
+    for AdaptiveMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    type Error = MineError<VALIDATION_WINDOW_SIZE, B>;
+
+    /// Recreate a mine from its `validation_blocks`, see [Mine::resume].
+    /// No validation is performed on the validation blocks.
+    /// Without a hint about the stream length [Strategy::TwoPtr] is always used,
+    /// see [AdaptiveMine::with_hint].
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        let strategy = Strategy::choose::<VALIDATION_WINDOW_SIZE, B>(None);
+        Self::from_window(VecDeque::from(validation_blocks), total_blocks, strategy)
+    }
+
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error> {
+        match self {
+            Self::Hash(mine) => mine.try_extend_one(new_block),
+            Self::TwoPtr(mine) => mine.try_extend_one(new_block),
+        }
+    }
+
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, Self::Error> {
+        match self {
+            Self::Hash(mine) => mine.try_extend_one_justified(new_block),
+            Self::TwoPtr(mine) => mine.try_extend_one_justified(new_block),
+        }
+    }
+
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error> {
+        match self {
+            Self::Hash(mine) => mine.peek(new_block),
+            Self::TwoPtr(mine) => mine.peek(new_block),
+        }
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a,
+    {
+        // both implementations iterate over the same type
+        match self {
+            Self::Hash(mine) => mine.validation_blocks.iter(),
+            Self::TwoPtr(mine) => mine.validation_blocks.iter(),
+        }
+    }
+
+    fn total_blocks(&self) -> u64 {
+        match self {
+            Self::Hash(mine) => mine.total_blocks,
+            Self::TwoPtr(mine) => mine.total_blocks,
+        }
+    }
+
+    fn ordered_validation_blocks(&self) -> Option<&[B]> {
+        match self {
+            Self::Hash(mine) => mine.ordered_validation_blocks(),
+            Self::TwoPtr(mine) => mine.ordered_validation_blocks(),
+        }
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        match self {
+            Self::Hash(mine) => mine.distinct_pair_sums(),
+            Self::TwoPtr(mine) => mine.distinct_pair_sums(),
+        }
+    }
+
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)> {
+        match self {
+            Self::Hash(mine) => mine.justifying_pair(new_block),
+            Self::TwoPtr(mine) => mine.justifying_pair(new_block),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::mine::MineError;
+    use crate::{
+        mine::Mine as MineTrait,
+        test_data::{example, FIRST_INVALID_BLOCK_NUMBER},
+    };
+
+    type Mine<const V: usize, B> = AdaptiveMine<V, B>;
+
+    #[test]
+    fn strategy_choice() {
+        assert_eq!(Strategy::choose::<5, u128>(None), Strategy::TwoPtr);
+        assert_eq!(Strategy::choose::<5, u128>(Some(1_000)), Strategy::Hash);
+        assert_eq!(
+            Strategy::choose::<5, u128>(Some(10)),
+            Strategy::TwoPtr,
+            "Short streams don't amortize the sum set"
+        );
+        assert_eq!(
+            Strategy::choose::<100, u128>(Some(1_000_000)),
+            Strategy::TwoPtr,
+            "Large windows don't fit the sum set"
+        );
+        assert_eq!(
+            Strategy::choose::<5, [u128; 2]>(Some(1_000)),
+            Strategy::TwoPtr,
+            "Large blocks are expensive to hash"
+        );
+
+        let mine = Mine::<5, u128>::new([35, 20, 15, 25, 47]);
+        assert_eq!(mine.strategy(), Strategy::TwoPtr);
+
+        let mine = Mine::<5, u128>::with_hint([35, 20, 15, 25, 47], 1_000);
+        assert_eq!(mine.strategy(), Strategy::Hash);
+    }
+
+    #[test]
+    fn example_with_both_strategies() {
+        for strategy in [Strategy::Hash, Strategy::TwoPtr] {
+            let initial_blocks = example::<u128>()[..5].try_into().unwrap();
+            let mut mine = Mine::<5, u128>::with_strategy(initial_blocks, strategy);
+            let result = mine.try_extend(example::<u128>()[5..].iter().copied());
+
+            assert_eq!(
+                result,
+                Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+            );
+        }
+    }
+
+    #[test]
+    fn runtime_window_size() {
+        for strategy in [Strategy::Hash, Strategy::TwoPtr] {
+            let blocks = example::<u128>();
+            let mut mine = Mine::with_window(blocks[..5].to_vec(), strategy);
+            assert_eq!(mine.validation_blocks().len(), 5);
+
+            let result = mine.try_extend(blocks[5..].iter().copied());
+            assert_eq!(
+                result,
+                Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+            );
+
+            mine.migrate(Strategy::TwoPtr);
+            assert_eq!(mine.try_extend_one(150 + 182), Ok(()));
+            assert_eq!(mine.total_blocks(), 15);
+        }
+    }
+
+    #[test]
+    fn migrate_mid_stream() {
+        let initial_blocks = example::<u128>()[..5].try_into().unwrap();
+        let mut mine = Mine::<5, u128>::with_strategy(initial_blocks, Strategy::Hash);
+
+        for (i, block) in example::<u128>()[5..14].iter().copied().enumerate() {
+            assert_eq!(mine.try_extend_one(block), Ok(()));
+
+            // keep flipping strategies while validating
+            let strategy = if i % 2 == 0 {
+                Strategy::TwoPtr
+            } else {
+                Strategy::Hash
+            };
+            mine.migrate(strategy);
+            assert_eq!(mine.strategy(), strategy);
+        }
+
+        assert_eq!(
+            mine.try_extend_one(127),
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER)),
+            "Block count must survive migrations"
+        );
+
+        mine.migrate(Strategy::Hash);
+        assert_eq!(
+            mine.try_extend_one(127),
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+        );
+        assert_eq!(mine.try_extend_one(150 + 182), Ok(()));
+        assert_eq!(mine.ordered_validation_blocks(), None);
+        assert!(mine.distinct_pair_sums().is_some());
+
+        mine.migrate(Strategy::TwoPtr);
+        assert_eq!(
+            mine.ordered_validation_blocks(),
+            Some(&[102, 117, 150, 182, 332][..])
+        );
+        assert_eq!(mine.distinct_pair_sums(), None);
+    }
+
+    #[test]
+    fn example_with_simple_construction() {
+        let result = Mine::<5, u128>::try_create_and_extend(example::<u128>());
+
+        assert_eq!(
+            result,
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+        );
+    }
+}
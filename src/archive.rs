@@ -0,0 +1,336 @@
+use crate::{
+    binary::{self, FixedWidthBlock},
+    mine::{try_extend_recorded, Mine},
+};
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+/// [Mine] keeping every accepted block, so blocks evicted from the validation window
+/// can still be queried by block number.
+///
+/// Blocks are kept in memory, unless a spill file is set with
+/// [ArchiveMine::with_spill_file]. Then the oldest blocks are moved to the file once
+/// more than the memory limit are held, and read back on demand. The file holds the
+/// blocks in little endian one after the other, so a block is found by its number alone.
+#[derive(Debug)]
+pub struct ArchiveMine<const VALIDATION_WINDOW_SIZE: usize, B, M> {
+    mine: M,
+    archive: Archive<B>,
+}
+
+/// Blocks archived by an [ArchiveMine], apart from its mine.
+#[derive(Debug)]
+struct Archive<B> {
+    /// Number of the oldest archived block, the first block in the window of the mine
+    /// the archive was created with.
+    first_block_number: u64,
+    spill: Option<File>,
+    /// Blocks in the spill file, from the oldest archived block on.
+    spilled_blocks: u64,
+    /// Archived blocks after the spilled ones.
+    memory: VecDeque<B>,
+    memory_limit: usize,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B, M> ArchiveMine<VALIDATION_WINDOW_SIZE, B, M>
+where
+    B: FixedWidthBlock,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+    /// Archive the blocks accepted by `mine`, starting with its validation window.
+    pub fn new(mine: M) -> Self {
+        let archive = Archive {
+            first_block_number: mine.total_blocks() + 1 - VALIDATION_WINDOW_SIZE as u64,
+            spill: None,
+            spilled_blocks: 0,
+            memory: mine.validation_blocks().copied().collect(),
+            memory_limit: usize::MAX,
+        };
+
+        Self { mine, archive }
+    }
+
+    /// Keep at most `memory_limit` blocks in memory, at least 1, and move older blocks to
+    /// the spill `file`. The file is emptied first.
+    /// # Panics
+    /// If a spill file is already set.
+    pub fn with_spill_file(mut self, file: File, memory_limit: usize) -> io::Result<Self> {
+        assert!(
+            self.archive.spill.is_none(),
+            "The archive already has a spill file."
+        );
+        file.set_len(0)?;
+        self.archive.spill = Some(file);
+        self.archive.memory_limit = memory_limit.max(1);
+        self.archive.spill_excess()?;
+        Ok(self)
+    }
+
+    /// Validate `new_block` and archive it if it's accepted, see [try_extend_recorded].
+    /// The error is a failure to spill, then the block is neither archived nor added to
+    /// the mine.
+    pub fn try_extend_one(&mut self, new_block: B) -> io::Result<Result<(), M::Error>> {
+        try_extend_recorded(
+            &mut self.mine,
+            new_block,
+            |_, block, validation| match validation {
+                Ok(()) => self.archive.push(*block),
+                Err(_) => Ok(()),
+            },
+        )
+    }
+
+    /// Same as [Mine::try_extend] with the blocks archived by [ArchiveMine::try_extend_one].
+    pub fn try_extend(
+        &mut self,
+        blocks: impl IntoIterator<Item = B>,
+    ) -> io::Result<Result<(), M::Error>> {
+        for block in blocks {
+            if let Err(error) = self.try_extend_one(block)? {
+                return Ok(Err(error));
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    pub fn mine(&self) -> &M {
+        &self.mine
+    }
+
+    /// Number of the oldest block in the archive. 1 unless the archive was created with
+    /// a [resumed](Mine::resume) mine.
+    pub fn first_block_number(&self) -> u64 {
+        self.archive.first_block_number
+    }
+
+    /// Number of the newest block in the archive.
+    pub fn total_blocks(&self) -> u64 {
+        self.mine.total_blocks()
+    }
+
+    /// Number of blocks moved to the spill file.
+    pub fn spilled_blocks(&self) -> u64 {
+        self.archive.spilled_blocks
+    }
+
+    /// The `block_number`-th block. `None` if it isn't archived.
+    pub fn block(&self, block_number: u64) -> io::Result<Option<B>> {
+        if !(self.archive.first_block_number..=self.total_blocks()).contains(&block_number) {
+            return Ok(None);
+        }
+        Ok(self.archive.read(block_number, 1)?.pop())
+    }
+
+    /// The validation window block number `block_number` was validated with, in arrival
+    /// order. `None` if the window isn't archived, initialization blocks have no window.
+    pub fn window(&self, block_number: u64) -> io::Result<Option<[B; VALIDATION_WINDOW_SIZE]>> {
+        let Some(first) = block_number.checked_sub(VALIDATION_WINDOW_SIZE as u64) else {
+            return Ok(None);
+        };
+        if first < self.archive.first_block_number || block_number > self.total_blocks() {
+            return Ok(None);
+        }
+
+        let window = self.archive.read(first, VALIDATION_WINDOW_SIZE)?;
+        Ok(Some(window.try_into().unwrap_or_else(|_| {
+            unreachable!("the window is archived")
+        })))
+    }
+
+    /// Numbers of the two blocks that summed up to block number `block_number`, the
+    /// older one first. `None` if its window isn't archived.
+    /// # Performance
+    /// O(VALIDATION_WINDOW_SIZE<sup>2</sup>), the pair is searched for in the window.
+    pub fn justifying_pair(&self, block_number: u64) -> io::Result<Option<(u64, u64)>> {
+        let (Some(window), Some(block)) = (self.window(block_number)?, self.block(block_number)?)
+        else {
+            return Ok(None);
+        };
+
+        let first_number = block_number - VALIDATION_WINDOW_SIZE as u64;
+        let pair = (0..VALIDATION_WINDOW_SIZE).find_map(|i| {
+            (i + 1..VALIDATION_WINDOW_SIZE)
+                .find(|&j| window[i] + window[j] == block)
+                .map(|j| (first_number + i as u64, first_number + j as u64))
+        });
+        Ok(pair)
+    }
+}
+
+impl<B: FixedWidthBlock> Archive<B> {
+    /// Archive an accepted block. A block that failed to spill isn't archived.
+    fn push(&mut self, block: B) -> io::Result<()> {
+        self.memory.push_back(block);
+        // spilling drains the memory only once the blocks are written
+        self.spill_excess().inspect_err(|_| {
+            self.memory.pop_back();
+        })
+    }
+
+    /// `count` archived blocks from block number `first` on.
+    fn read(&self, first: u64, count: usize) -> io::Result<Vec<B>> {
+        let mut blocks = Vec::with_capacity(count);
+        let offset = first - self.first_block_number;
+
+        if offset < self.spilled_blocks {
+            let spilled = count.min((self.spilled_blocks - offset) as usize);
+            let width = u64::from(binary::width::<B>());
+            let mut file = self
+                .spill
+                .as_ref()
+                .expect("spilled blocks are in the spill file");
+            let mut bytes = vec![0; spilled * width as usize];
+            file.seek(SeekFrom::Start(offset * width))?;
+            file.read_exact(&mut bytes)?;
+
+            blocks.extend(bytes.chunks(width as usize).map(|bytes| {
+                let mut block = B::Bytes::default();
+                block.as_mut().copy_from_slice(bytes);
+                B::from_le_bytes(block)
+            }));
+        }
+
+        let in_memory = offset.saturating_sub(self.spilled_blocks) as usize;
+        blocks.extend(self.memory.range(in_memory..).take(count - blocks.len()));
+        Ok(blocks)
+    }
+
+    /// Move blocks to the spill file until half the memory limit is left, once the limit
+    /// is exceeded.
+    fn spill_excess(&mut self) -> io::Result<()> {
+        let Some(file) = &mut self.spill else {
+            return Ok(());
+        };
+        if self.memory.len() <= self.memory_limit {
+            return Ok(());
+        }
+
+        let spilled = self.memory.len() - self.memory_limit / 2;
+        let mut bytes = Vec::with_capacity(spilled * usize::from(binary::width::<B>()));
+        for block in self.memory.range(..spilled) {
+            bytes.extend_from_slice(block.to_le_bytes().as_ref());
+        }
+
+        // not the end of the file, which may hold a partial write of a failed spill
+        let width = u64::from(binary::width::<B>());
+        file.seek(SeekFrom::Start(self.spilled_blocks * width))?;
+        file.write_all(&bytes)?;
+        self.memory.drain(..spilled);
+        self.spilled_blocks += spilled as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine, mine::MineError, test_data::RECOVERING_EXAMPLE,
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn archive(memory_limit: Option<usize>) -> ArchiveMine<5, u64, HashMine<5, u64>> {
+        let mine = HashMine::new(RECOVERING_EXAMPLE[..5].try_into().unwrap());
+        let mut archive = ArchiveMine::new(mine);
+        if let Some(memory_limit) = memory_limit {
+            let file = tempfile::tempfile().unwrap();
+            archive = archive.with_spill_file(file, memory_limit).unwrap();
+        }
+
+        for block in &RECOVERING_EXAMPLE[5..] {
+            let result = archive.try_extend_one(*block).unwrap();
+            assert_eq!(result.is_err(), *block == 127);
+        }
+        archive
+    }
+
+    #[test]
+    fn queries() {
+        let accepted: Vec<u64> = RECOVERING_EXAMPLE
+            .into_iter()
+            .filter(|block| *block != 127)
+            .collect();
+
+        for memory_limit in [None, Some(1), Some(4), Some(100)] {
+            let archive = archive(memory_limit);
+            assert_eq!(archive.total_blocks(), 17);
+
+            let blocks: Vec<_> = (1..=17)
+                .map(|block_number| archive.block(block_number).unwrap().unwrap())
+                .collect();
+            assert_eq!(blocks, accepted);
+            assert_eq!(archive.block(0).unwrap(), None);
+            assert_eq!(archive.block(18).unwrap(), None);
+
+            assert_eq!(archive.window(5).unwrap(), None);
+            assert_eq!(archive.window(6).unwrap(), Some([35, 20, 15, 25, 47]));
+            assert_eq!(archive.window(17).unwrap(), Some([117, 150, 182, 219, 332]));
+            assert_eq!(archive.window(18).unwrap(), None);
+
+            assert_eq!(archive.justifying_pair(6).unwrap(), Some((3, 4)));
+            // 182 + 219
+            assert_eq!(archive.justifying_pair(17).unwrap(), Some((14, 15)));
+            assert_eq!(archive.justifying_pair(3).unwrap(), None);
+        }
+
+        assert_eq!(archive(None).spilled_blocks(), 0);
+        let archive = archive(Some(4));
+        assert_eq!(archive.spilled_blocks(), 15);
+        assert_eq!(archive.archive.memory.len(), 2);
+    }
+
+    #[test]
+    fn failed_spill() {
+        let mine = HashMine::<5, u64>::new(RECOVERING_EXAMPLE[..5].try_into().unwrap());
+        let mut archive = ArchiveMine::new(mine)
+            .with_spill_file(tempfile::tempfile().unwrap(), 4)
+            .unwrap();
+        assert_eq!(archive.spilled_blocks(), 3);
+
+        // garbage past the spilled blocks, as left by a partial write
+        let file = archive.archive.spill.as_mut().unwrap();
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(&[0xff; 5]).unwrap();
+
+        assert_eq!(
+            archive
+                .try_extend(RECOVERING_EXAMPLE[5..14].iter().copied())
+                .unwrap(),
+            Ok(())
+        );
+        let blocks: Vec<_> = (1..=14)
+            .map(|block_number| archive.block(block_number).unwrap().unwrap())
+            .collect();
+        assert_eq!(blocks, RECOVERING_EXAMPLE[..14]);
+    }
+
+    #[test]
+    fn resumed_mine() {
+        let mine = TwoPtrMine::<3, u64>::resume([1, 2, 3], 1_000);
+        let mut archive = ArchiveMine::new(mine)
+            .with_spill_file(tempfile::tempfile().unwrap(), 2)
+            .unwrap();
+        assert_eq!(archive.first_block_number(), 998);
+        assert_eq!(archive.try_extend([5, 8]).unwrap(), Ok(()));
+        assert_eq!(
+            archive.try_extend_one(20).unwrap(),
+            Err(MineError::InvalidBlock(20, 1_003))
+        );
+
+        assert_eq!(archive.block(997).unwrap(), None);
+        assert_eq!(archive.block(998).unwrap(), Some(1));
+        assert_eq!(archive.window(1_002).unwrap(), Some([2, 3, 5]));
+        assert_eq!(
+            archive.justifying_pair(1_002).unwrap(),
+            Some((1_000, 1_001))
+        );
+        assert_eq!(archive.window(1_000).unwrap(), None);
+    }
+}
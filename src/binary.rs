@@ -0,0 +1,648 @@
+use crate::{
+    mine::Block,
+    parse::{BlockReader, ParseError},
+};
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+/// Identifies a block file.
+const MAGIC: [u8; 4] = *b"TMMT";
+/// Version of the format written by [BlockFileWriter].
+const VERSION: u8 = 1;
+/// Size of the encoded [Header] in bytes.
+pub const HEADER_LEN: usize = 28;
+
+/// Set in the flags byte of the header for signed blocks.
+pub(crate) const FLAG_SIGNED: u8 = 0b01;
+/// Set in the flags byte of the header for big endian blocks.
+const FLAG_BIG_ENDIAN: u8 = 0b10;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+    #[error("Input is not a block file.")]
+    InvalidMagic,
+    #[error("Unsupported block file version {0}.")]
+    UnsupportedVersion(u8),
+    #[error("Invalid block file header: {0}.")]
+    InvalidHeader(&'static str),
+    #[error(
+        "Block file holds {width} byte {} blocks, expected {expected_width} byte {} blocks.",
+        signedness(*.signed),
+        signedness(*.expected_signed)
+    )]
+    BlockTypeMismatch {
+        width: u8,
+        signed: bool,
+        expected_width: u8,
+        expected_signed: bool,
+    },
+    #[error("Block file ends after {read} of {expected} blocks.")]
+    Truncated { read: u64, expected: u64 },
+    #[error("Block file has bytes after its {blocks} blocks.")]
+    TrailingBytes { blocks: u64 },
+    #[error(
+        "Block file checksum {expected:#010x} doesn't match the blocks checksum {actual:#010x}."
+    )]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error("Failed to read or write a block file: {0}.")]
+    Io(io::ErrorKind),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+impl From<io::Error> for BinaryError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error.kind())
+    }
+}
+
+fn signedness(signed: bool) -> &'static str {
+    if signed {
+        "signed"
+    } else {
+        "unsigned"
+    }
+}
+
+/// [Block] with a fixed width binary representation, implemented for
+/// all the primitive integers.
+pub trait FixedWidthBlock: Block + Copy {
+    /// Bytes of a single block.
+    type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+    const SIGNED: bool;
+
+    fn from_le_bytes(bytes: Self::Bytes) -> Self;
+    fn from_be_bytes(bytes: Self::Bytes) -> Self;
+    fn to_le_bytes(self) -> Self::Bytes;
+}
+
+macro_rules! fixed_width_block {
+    ($($block:ty: $signed:literal),*) => {
+        $(
+            impl FixedWidthBlock for $block {
+                type Bytes = [u8; std::mem::size_of::<$block>()];
+                const SIGNED: bool = $signed;
+
+                fn from_le_bytes(bytes: Self::Bytes) -> Self {
+                    <$block>::from_le_bytes(bytes)
+                }
+
+                fn from_be_bytes(bytes: Self::Bytes) -> Self {
+                    <$block>::from_be_bytes(bytes)
+                }
+
+                fn to_le_bytes(self) -> Self::Bytes {
+                    <$block>::to_le_bytes(self)
+                }
+            }
+        )*
+    };
+}
+
+fixed_width_block!(
+    u8: false, u16: false, u32: false, u64: false, u128: false,
+    i8: true, i16: true, i32: true, i64: true, i128: true
+);
+
+/// Width of `B` in bytes.
+pub(crate) fn width<B: FixedWidthBlock>() -> u8 {
+    std::mem::size_of::<B::Bytes>() as u8
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// Header of a block file. Encoded in [HEADER_LEN] bytes:
+///
+/// | bytes | content                                       |
+/// |-------|-----------------------------------------------|
+/// | 0-3   | magic `TMMT`                                  |
+/// | 4     | format version                                |
+/// | 5     | block width in bytes: 1, 2, 4, 8 or 16        |
+/// | 6     | flags: `0b01` signed blocks, `0b10` big endian |
+/// | 7     | reserved, zero                                |
+/// | 8-15  | validation window size                        |
+/// | 16-23 | number of blocks                              |
+/// | 24-27 | CRC-32 of the blocks                          |
+///
+/// Numbers in the header are little endian. The header is followed by the packed blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Width of a block in bytes.
+    pub width: u8,
+    pub signed: bool,
+    pub endianness: Endianness,
+    /// Size of the validation window the blocks are meant to be validated with.
+    pub window_size: u64,
+    /// Number of blocks in the file.
+    pub blocks: u64,
+    /// CRC-32 (IEEE) of the blocks, as stored in the file.
+    pub checksum: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut flags = 0;
+        if self.signed {
+            flags |= FLAG_SIGNED;
+        }
+        if self.endianness == Endianness::Big {
+            flags |= FLAG_BIG_ENDIAN;
+        }
+
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = VERSION;
+        bytes[5] = self.width;
+        bytes[6] = flags;
+        bytes[8..16].copy_from_slice(&self.window_size.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.blocks.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8; HEADER_LEN]) -> Result<Self, BinaryError> {
+        if bytes[0..4] != MAGIC {
+            return Err(BinaryError::InvalidMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(BinaryError::UnsupportedVersion(bytes[4]));
+        }
+
+        let width = bytes[5];
+        if !matches!(width, 1 | 2 | 4 | 8 | 16) {
+            return Err(BinaryError::InvalidHeader("unsupported block width"));
+        }
+        let flags = bytes[6];
+        if flags & !(FLAG_SIGNED | FLAG_BIG_ENDIAN) != 0 || bytes[7] != 0 {
+            return Err(BinaryError::InvalidHeader("unknown flags"));
+        }
+
+        let u64_at =
+            |start: usize| u64::from_le_bytes(bytes[start..start + 8].try_into().expect("8 bytes"));
+
+        Ok(Self {
+            width,
+            signed: flags & FLAG_SIGNED != 0,
+            endianness: if flags & FLAG_BIG_ENDIAN != 0 {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            },
+            window_size: u64_at(8),
+            blocks: u64_at(16),
+            checksum: u32::from_le_bytes(bytes[24..28].try_into().expect("4 bytes")),
+        })
+    }
+}
+
+/// Writes blocks to a block file. See [Header] for the format.
+///
+/// Blocks are always packed little endian, big endian files are only read.
+///
+/// The block count and the checksum are written to the header by [BlockFileWriter::finish],
+/// seeking back to it. So the writer can't write to a pipe or the standard output, write
+/// to a file or a [Cursor](std::io::Cursor) first.
+#[derive(Debug)]
+pub struct BlockFileWriter<W: Write + Seek, B> {
+    writer: W,
+    /// Position of the header in the `writer`.
+    start: u64,
+    window_size: u64,
+    blocks: u64,
+    checksum: Crc32,
+    _block: PhantomData<fn(B)>,
+}
+
+impl<W, B> BlockFileWriter<W, B>
+where
+    W: Write + Seek,
+    B: FixedWidthBlock,
+{
+    /// Start a block file at the current position of the `writer`.
+    pub fn new(mut writer: W, window_size: u64) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        // placeholder, rewritten by finish
+        writer.write_all(&[0; HEADER_LEN])?;
+
+        Ok(Self {
+            writer,
+            start,
+            window_size,
+            blocks: 0,
+            checksum: Crc32::new(),
+            _block: PhantomData,
+        })
+    }
+
+    pub fn write_block(&mut self, block: B) -> io::Result<()> {
+        let bytes = block.to_le_bytes();
+        self.writer.write_all(bytes.as_ref())?;
+        self.checksum.update(bytes.as_ref());
+        self.blocks += 1;
+        Ok(())
+    }
+
+    pub fn write_blocks(&mut self, blocks: impl IntoIterator<Item = B>) -> io::Result<()> {
+        blocks
+            .into_iter()
+            .try_for_each(|block| self.write_block(block))
+    }
+
+    /// Write the header and return the `writer`, positioned after the last block.
+    pub fn finish(mut self) -> io::Result<W> {
+        let header = Header {
+            width: width::<B>(),
+            signed: B::SIGNED,
+            endianness: Endianness::Little,
+            window_size: self.window_size,
+            blocks: self.blocks,
+            checksum: self.checksum.finish(),
+        };
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        self.writer.write_all(&header.encode())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Iterator over the blocks in a block file. See [Header] for the format.
+///
+/// Yields blocks, not results, so it can be passed straight to
+/// [Mine::try_extend](crate::mine::Mine::try_extend). Iteration stops at the first
+/// failure to read a block, the failure and a checksum mismatch are reported by
+/// [BlockFileReader::finish]. Check it before trusting the outcome of validation,
+/// a short or corrupted file can change it.
+///
+/// The checksum covers every block, so it's only checked by [BlockFileReader::finish],
+/// which reads the blocks left. A consumer stopping early, at the first invalid block for
+/// instance, has to call it to know whether the file is intact.
+#[derive(Debug)]
+pub struct BlockFileReader<R, B> {
+    reader: R,
+    header: Header,
+    /// Number of blocks read so far.
+    read: u64,
+    checksum: Crc32,
+    error: Option<BinaryError>,
+    _block: PhantomData<fn() -> B>,
+}
+
+impl<R, B> BlockFileReader<R, B>
+where
+    R: BufRead,
+    B: FixedWidthBlock,
+{
+    /// Read the header and check that the file holds blocks of type `B`.
+    pub fn new(mut reader: R) -> Result<Self, BinaryError> {
+        let mut bytes = [0; HEADER_LEN];
+        reader.read_exact(&mut bytes).map_err(|error| {
+            if error.kind() == io::ErrorKind::UnexpectedEof {
+                BinaryError::InvalidMagic
+            } else {
+                error.into()
+            }
+        })?;
+        let header = Header::decode(&bytes)?;
+
+        if header.width != width::<B>() || header.signed != B::SIGNED {
+            return Err(BinaryError::BlockTypeMismatch {
+                width: header.width,
+                signed: header.signed,
+                expected_width: width::<B>(),
+                expected_signed: B::SIGNED,
+            });
+        }
+
+        Ok(Self {
+            reader,
+            header,
+            read: 0,
+            checksum: Crc32::new(),
+            error: None,
+            _block: PhantomData,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Read the blocks left and check the checksum of the file, and that nothing
+    /// follows the blocks.
+    pub fn finish(mut self) -> Result<Header, BinaryError> {
+        self.by_ref().for_each(drop);
+
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !self.reader.fill_buf()?.is_empty() {
+            return Err(BinaryError::TrailingBytes {
+                blocks: self.header.blocks,
+            });
+        }
+
+        let actual = self.checksum.finish();
+        if actual != self.header.checksum {
+            return Err(BinaryError::ChecksumMismatch {
+                expected: self.header.checksum,
+                actual,
+            });
+        }
+
+        Ok(self.header)
+    }
+}
+
+impl<R, B> Iterator for BlockFileReader<R, B>
+where
+    R: BufRead,
+    B: FixedWidthBlock,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.read == self.header.blocks || self.error.is_some() {
+            return None;
+        }
+
+        let mut bytes = B::Bytes::default();
+        if let Err(error) = self.reader.read_exact(bytes.as_mut()) {
+            self.error = Some(if error.kind() == io::ErrorKind::UnexpectedEof {
+                BinaryError::Truncated {
+                    read: self.read,
+                    expected: self.header.blocks,
+                }
+            } else {
+                error.into()
+            });
+            return None;
+        }
+
+        self.checksum.update(bytes.as_ref());
+        self.read += 1;
+
+        Some(match self.header.endianness {
+            Endianness::Little => B::from_le_bytes(bytes),
+            Endianness::Big => B::from_be_bytes(bytes),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = usize::try_from(self.header.blocks - self.read).ok();
+        (0, left)
+    }
+}
+
+/// Convert text `input` to a block file, see [BlockReader] for the text format.
+/// Returns the number of blocks converted.
+pub fn text_to_binary<B, W>(
+    input: impl BufRead,
+    output: W,
+    window_size: u64,
+) -> Result<u64, BinaryError>
+where
+    B: FixedWidthBlock + FromStr,
+    B::Err: Display,
+    W: Write + Seek,
+{
+    let mut writer = BlockFileWriter::<_, B>::new(output, window_size)?;
+    for block in BlockReader::new(input) {
+        writer.write_block(block?)?;
+    }
+
+    let blocks = writer.blocks;
+    writer.finish()?;
+    Ok(blocks)
+}
+
+/// Convert a block file to text, one block per line. Returns the header of the file.
+pub fn binary_to_text<B>(input: impl BufRead, mut output: impl Write) -> Result<Header, BinaryError>
+where
+    B: FixedWidthBlock + Display,
+{
+    let mut reader = BlockFileReader::<_, B>::new(input)?;
+    for block in reader.by_ref() {
+        writeln!(output, "{block}")?;
+    }
+    output.flush()?;
+
+    reader.finish()
+}
+
+/// CRC-32 with the IEEE polynomial, as used by zip and png.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crc32(u32);
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Self(!0)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = CRC32_TABLE[((self.0 ^ u32::from(*byte)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::{Mine, MineError},
+        test_data::{example, FIRST_INVALID_BLOCK_NUMBER},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn write<B: FixedWidthBlock>(blocks: &[B], window_size: u64) -> Vec<u8> {
+        let mut writer = BlockFileWriter::new(Cursor::new(Vec::new()), window_size).unwrap();
+        writer.write_blocks(blocks.iter().copied()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = write(&example::<u128>(), 5);
+        assert_eq!(file.len(), HEADER_LEN + 16 * example::<u128>().len());
+        assert_eq!(&file[HEADER_LEN..HEADER_LEN + 16], &35u128.to_le_bytes());
+
+        let mut reader = BlockFileReader::<_, u128>::new(&file[..]).unwrap();
+        assert_eq!(reader.header().window_size, 5);
+        assert_eq!(reader.header().blocks, 20);
+        assert_eq!(reader.by_ref().collect::<Vec<_>>(), example::<u128>());
+        assert!(reader.finish().is_ok());
+
+        let blocks = [-3i16, 0, i16::MAX, i16::MIN];
+        let file = write(&blocks, 2);
+        let mut reader = BlockFileReader::<_, i16>::new(&file[..]).unwrap();
+        assert_eq!(reader.by_ref().collect::<Vec<_>>(), blocks);
+        assert!(reader.finish().unwrap().signed);
+
+        let mut checksum = Crc32::new();
+        checksum.update(&[1, 2]);
+        let header = Header {
+            width: 2,
+            signed: false,
+            endianness: Endianness::Big,
+            window_size: 2,
+            blocks: 1,
+            checksum: checksum.finish(),
+        };
+        let file = [&header.encode()[..], &[1, 2]].concat();
+        let mut reader = BlockFileReader::<_, u16>::new(&file[..]).unwrap();
+        assert_eq!(reader.next(), Some(0x0102));
+        assert_eq!(reader.finish(), Ok(header));
+    }
+
+    #[test]
+    fn validation() {
+        let file = write(&example::<u128>(), 5);
+
+        let mut reader = BlockFileReader::<_, u128>::new(&file[..]).unwrap();
+        let result = HashMine::<5, u128>::try_create_and_extend(&mut reader);
+        assert!(reader.finish().is_ok());
+        assert_eq!(
+            result,
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+        );
+
+        let mut reader = BlockFileReader::<_, u128>::new(&file[..]).unwrap();
+        let mut mine =
+            TwoPtrMine::<5, u128>::try_new(reader.by_ref().take(5).collect::<Vec<_>>()).unwrap();
+        assert_eq!(mine.try_extend(reader.by_ref().take(9)), Ok(()));
+        assert_eq!(mine.total_blocks(), 14);
+    }
+
+    #[test]
+    fn errors() {
+        let file = write(&example::<u128>(), 5);
+
+        assert_eq!(
+            BlockFileReader::<_, u64>::new(&file[..]).unwrap_err(),
+            BinaryError::BlockTypeMismatch {
+                width: 16,
+                signed: false,
+                expected_width: 8,
+                expected_signed: false
+            }
+        );
+        assert_eq!(
+            BlockFileReader::<_, i128>::new(&file[..]).unwrap_err(),
+            BinaryError::BlockTypeMismatch {
+                width: 16,
+                signed: false,
+                expected_width: 16,
+                expected_signed: true
+            }
+        );
+        assert_eq!(
+            BlockFileReader::<_, u128>::new(&b"35\n20\n"[..]).unwrap_err(),
+            BinaryError::InvalidMagic
+        );
+
+        let mut corrupted = file.clone();
+        corrupted[HEADER_LEN] ^= 1;
+        let mut reader = BlockFileReader::<_, u128>::new(&corrupted[..]).unwrap();
+        assert_eq!(reader.next(), Some(34));
+        assert!(matches!(
+            reader.finish(),
+            Err(BinaryError::ChecksumMismatch { .. })
+        ));
+
+        let trailing = [&file[..], b"\n"].concat();
+        let mut reader = BlockFileReader::<_, u128>::new(&trailing[..]).unwrap();
+        assert_eq!(reader.by_ref().count(), 20);
+        assert_eq!(
+            reader.finish(),
+            Err(BinaryError::TrailingBytes { blocks: 20 })
+        );
+
+        let truncated = &file[..file.len() - 1];
+        let mut reader = BlockFileReader::<_, u128>::new(truncated).unwrap();
+        assert_eq!(reader.by_ref().count(), 19);
+        assert_eq!(
+            reader.finish(),
+            Err(BinaryError::Truncated {
+                read: 19,
+                expected: 20
+            })
+        );
+    }
+
+    #[test]
+    fn conversion() {
+        let text = "# example\n35\n20\n15\n\n25\r\n47\n";
+
+        let mut file = Cursor::new(Vec::new());
+        assert_eq!(
+            text_to_binary::<u32, _>(text.as_bytes(), &mut file, 5),
+            Ok(5)
+        );
+        let file = file.into_inner();
+        assert_eq!(file.len(), HEADER_LEN + 4 * 5);
+
+        let mut output = Vec::new();
+        let header = binary_to_text::<u32>(&file[..], &mut output).unwrap();
+        assert_eq!(header.blocks, 5);
+        assert_eq!(String::from_utf8(output).unwrap(), "35\n20\n15\n25\n47\n");
+
+        let result = text_to_binary::<u8, _>("1\n256\n".as_bytes(), Cursor::new(Vec::new()), 5);
+        assert!(matches!(
+            result,
+            Err(BinaryError::Parse(ParseError { line: 2, .. }))
+        ));
+    }
+}
@@ -0,0 +1,385 @@
+use crate::mine::{try_extend_recorded, Block, Mine};
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+    ops::Add,
+};
+
+use thiserror::Error;
+
+/// First line of a certificate.
+const MAGIC: &str = "tmmt certificate 1";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    #[error("Input is not a certificate.")]
+    InvalidMagic,
+    #[error(
+        "Certificate is for a validation window of {window_size} blocks, expected {expected}."
+    )]
+    WindowSizeMismatch { window_size: usize, expected: usize },
+    #[error("Invalid certificate line {line}: {reason}.")]
+    InvalidLine { line: usize, reason: String },
+    #[error(
+        "Offsets {offsets:?} of block number {block_number} aren't two distinct blocks in the validation window."
+    )]
+    InvalidOffsets {
+        block_number: u64,
+        offsets: (usize, usize),
+    },
+    #[error("Block number {block_number} is not the sum of the blocks at offsets {offsets:?}.")]
+    WrongSum {
+        block_number: u64,
+        offsets: (usize, usize),
+    },
+    #[error("Certificate has no proof for block number {0}.")]
+    MissingProof(u64),
+    #[error("Certificate has more proofs than blocks, the stream ends after {0} blocks.")]
+    ExtraProofs(u64),
+    #[error("Stream ends after {0} blocks, before the validation window is full.")]
+    TooShort(u64),
+    #[error("Failed to read a certificate: {0}.")]
+    Io(io::ErrorKind),
+}
+
+impl From<io::Error> for CertificateError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error.kind())
+    }
+}
+
+/// Writes a validity certificate while a mine validates a stream.
+///
+/// A certificate proves every accepted block with the offsets of its two summands,
+/// counted back from the block. Offset 1 is the previous block, offset
+/// [VALIDATION_WINDOW_SIZE] the oldest one in the window:
+///
+/// ```text
+/// tmmt certificate 1
+/// window 5
+/// 2 3
+/// 2 4
+/// ```
+///
+/// Checking a certificate with [verify] is constant time per block, while validation
+/// with a [Mine] isn't. Rejected blocks aren't certified: the certificate is for the
+/// stream of accepted blocks, starting with the validation window of the mine.
+///
+/// [VALIDATION_WINDOW_SIZE]: CertificateWriter<VALIDATION_WINDOW_SIZE>
+#[derive(Debug)]
+pub struct CertificateWriter<const VALIDATION_WINDOW_SIZE: usize, W> {
+    writer: W,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, W: Write> CertificateWriter<VALIDATION_WINDOW_SIZE, W> {
+    /// Start a certificate by writing its header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{MAGIC}")?;
+        writeln!(writer, "window {VALIDATION_WINDOW_SIZE}")?;
+        Ok(Self { writer })
+    }
+
+    /// Validate `new_block` and certify it if it's accepted, see [try_extend_recorded].
+    ///
+    /// The summands come from [Mine::justifying_pair], or a quadratic search of the
+    /// window for mines that can't tell.
+    /// # Panics
+    /// If the mine accepts a block that isn't the sum of two blocks in its window.
+    pub fn try_extend_one<B, M>(
+        &mut self,
+        mine: &mut M,
+        new_block: B,
+    ) -> io::Result<Result<(), M::Error>>
+    where
+        B: Block,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+        for<'a> &'a B: Add<&'a B, Output = B>,
+    {
+        try_extend_recorded(mine, new_block, |mine, block, validation| {
+            if validation.is_err() {
+                return Ok(());
+            }
+            let (first, second) = justifying_offsets(mine, block)
+                .expect("an accepted block is the sum of two blocks in the window");
+            writeln!(self.writer, "{first} {second}")
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Offsets of two blocks in the window of the `mine` summing up to `new_block`,
+/// the smaller one first.
+pub(crate) fn justifying_offsets<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+    mine: &M,
+    new_block: &B,
+) -> Option<(usize, usize)>
+where
+    B: Block,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let window: Vec<&B> = mine.validation_blocks().collect();
+    let offset = |index: usize| window.len() - index;
+
+    let from_pair = mine.justifying_pair(new_block).and_then(|(first, second)| {
+        let i = window.iter().rposition(|block| *block == first)?;
+        let j = (0..window.len())
+            .rev()
+            .find(|&j| j != i && window[j] == second)?;
+        Some((offset(i.max(j)), offset(i.min(j))))
+    });
+
+    from_pair.or_else(|| {
+        window.iter().enumerate().find_map(|(i, first)| {
+            (i + 1..window.len())
+                .find(|&j| *first + window[j] == *new_block)
+                .map(|j| (offset(j), offset(i)))
+        })
+    })
+}
+
+/// Check a stream of `blocks` against a certificate written by [CertificateWriter].
+/// Returns the number of blocks in the stream.
+///
+/// The first [VALIDATION_WINDOW_SIZE] blocks are the initialization blocks, every
+/// following block must have a proof. Each block takes a single addition, and only the
+/// validation window is kept in memory.
+///
+/// [VALIDATION_WINDOW_SIZE]: CertificateWriter<VALIDATION_WINDOW_SIZE>
+pub fn verify<const VALIDATION_WINDOW_SIZE: usize, B>(
+    blocks: impl IntoIterator<Item = B>,
+    certificate: impl BufRead,
+) -> Result<u64, CertificateError>
+where
+    B: Block,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let mut lines = certificate
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line));
+
+    let (_, magic) = lines.next().ok_or(CertificateError::InvalidMagic)?;
+    if magic?.trim_end() != MAGIC {
+        return Err(CertificateError::InvalidMagic);
+    }
+    let (line, window) = lines.next().ok_or(CertificateError::InvalidMagic)?;
+    let window_size = window?
+        .trim()
+        .strip_prefix("window ")
+        .and_then(|size| size.trim().parse().ok())
+        .ok_or_else(|| CertificateError::InvalidLine {
+            line,
+            reason: "expected `window <number>`".to_owned(),
+        })?;
+    if window_size != VALIDATION_WINDOW_SIZE {
+        return Err(CertificateError::WindowSizeMismatch {
+            window_size,
+            expected: VALIDATION_WINDOW_SIZE,
+        });
+    }
+
+    let mut window = VecDeque::with_capacity(VALIDATION_WINDOW_SIZE);
+    let mut total_blocks = 0;
+
+    for block in blocks {
+        total_blocks += 1;
+        if window.len() < VALIDATION_WINDOW_SIZE {
+            window.push_back(block);
+            continue;
+        }
+
+        let (line, proof) = lines
+            .next()
+            .ok_or(CertificateError::MissingProof(total_blocks))?;
+        let offsets = parse_offsets(line, &proof?)?;
+
+        let (first, second) = offsets;
+        let in_window = 1..=VALIDATION_WINDOW_SIZE;
+        if first == second || !in_window.contains(&first) || !in_window.contains(&second) {
+            return Err(CertificateError::InvalidOffsets {
+                block_number: total_blocks,
+                offsets,
+            });
+        }
+
+        let summand = |offset| &window[VALIDATION_WINDOW_SIZE - offset];
+        if summand(first) + summand(second) != block {
+            return Err(CertificateError::WrongSum {
+                block_number: total_blocks,
+                offsets,
+            });
+        }
+
+        window.pop_front();
+        window.push_back(block);
+    }
+
+    if total_blocks < VALIDATION_WINDOW_SIZE as u64 {
+        return Err(CertificateError::TooShort(total_blocks));
+    }
+    if lines.next().is_some() {
+        return Err(CertificateError::ExtraProofs(total_blocks));
+    }
+
+    Ok(total_blocks)
+}
+
+/// Offsets of a `first second` proof line.
+fn parse_offsets(line: usize, text: &str) -> Result<(usize, usize), CertificateError> {
+    let mut fields = text.split_whitespace().map(str::parse);
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(Ok(first)), Some(Ok(second)), None) => Ok((first, second)),
+        _ => Err(CertificateError::InvalidLine {
+            line,
+            reason: "expected `<offset> <offset>`".to_owned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::MineError,
+        test_data::{EXAMPLE, FIRST_INVALID_BLOCK_NUMBER},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn certify<M: Mine<5, u64>>() -> String {
+        let mut mine = M::new(EXAMPLE[..5].try_into().unwrap());
+        let mut writer = CertificateWriter::<5, _>::new(Vec::new()).unwrap();
+        let results: Vec<_> = EXAMPLE[5..15]
+            .iter()
+            .map(|block| writer.try_extend_one(&mut mine, *block).unwrap().is_ok())
+            .collect();
+        assert_eq!(results.iter().filter(|accepted| !**accepted).count(), 1);
+
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    fn verify_text(blocks: &[u64], certificate: &str) -> Result<u64, CertificateError> {
+        verify::<5, u64>(blocks.iter().copied(), certificate.as_bytes())
+    }
+
+    #[test]
+    fn certify_and_verify() {
+        let certificate = certify::<HashMine<5, u64>>();
+        assert!(certificate.starts_with("tmmt certificate 1\nwindow 5\n2 3\n2 4\n"));
+        assert_eq!(certificate.lines().count(), 2 + 9);
+        assert_eq!(verify_text(&EXAMPLE[..14], &certificate), Ok(14));
+
+        let certificate = certify::<TwoPtrMine<5, u64>>();
+        assert_eq!(verify_text(&EXAMPLE[..14], &certificate), Ok(14));
+
+        assert_eq!(
+            verify_text(&EXAMPLE, &certificate),
+            Err(CertificateError::MissingProof(FIRST_INVALID_BLOCK_NUMBER))
+        );
+        assert_eq!(
+            verify_text(&EXAMPLE[..4], "tmmt certificate 1\nwindow 5\n"),
+            Err(CertificateError::TooShort(4))
+        );
+    }
+
+    #[test]
+    fn duplicate_summands() {
+        let mut mine = TwoPtrMine::<3, u64>::new([4, 1, 4]);
+        let mut writer = CertificateWriter::<3, _>::new(Vec::new()).unwrap();
+        assert_eq!(writer.try_extend_one(&mut mine, 8).unwrap(), Ok(()));
+        assert_eq!(
+            writer.try_extend_one(&mut mine, 3).unwrap(),
+            Err(MineError::InvalidBlock(3, 5))
+        );
+
+        let certificate = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(certificate, "tmmt certificate 1\nwindow 3\n1 3\n");
+        assert_eq!(
+            verify::<3, u64>([4, 1, 4, 8], certificate.as_bytes()),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn failed_write() {
+        let mut mine = TwoPtrMine::<3, u64>::new([4, 1, 4]);
+        let mut buffer = [0; "tmmt certificate 1\nwindow 3\n".len()];
+        let mut writer = CertificateWriter::<3, _>::new(&mut buffer[..]).unwrap();
+        assert!(writer.try_extend_one(&mut mine, 8).is_err());
+        assert_eq!(
+            mine.total_blocks(),
+            3,
+            "Uncertified blocks stay out of the mine"
+        );
+    }
+
+    #[test]
+    fn forged_certificates() {
+        let certificate = certify::<HashMine<5, u64>>();
+        let blocks = &EXAMPLE[..14];
+        let forge = |line: usize, proof: &str| {
+            let mut lines: Vec<_> = certificate.lines().collect();
+            lines[line + 1] = proof;
+            verify_text(blocks, &lines.join("\n"))
+        };
+
+        assert_eq!(
+            forge(1, "1 2"),
+            Err(CertificateError::WrongSum {
+                block_number: 6,
+                offsets: (1, 2)
+            })
+        );
+        for offsets in [(0, 1), (3, 3), (2, 6)] {
+            assert_eq!(
+                forge(1, &format!("{} {}", offsets.0, offsets.1)),
+                Err(CertificateError::InvalidOffsets {
+                    block_number: 6,
+                    offsets
+                })
+            );
+        }
+        assert_eq!(
+            forge(2, "1 5 0"),
+            Err(CertificateError::InvalidLine {
+                line: 4,
+                reason: "expected `<offset> <offset>`".to_owned()
+            })
+        );
+
+        let mut forged_block = blocks.to_vec();
+        forged_block[8] = 96;
+        assert_eq!(
+            verify_text(&forged_block, &certificate),
+            Err(CertificateError::WrongSum {
+                block_number: 9,
+                offsets: (3, 5)
+            })
+        );
+
+        assert_eq!(
+            verify_text(&blocks[..13], &certificate),
+            Err(CertificateError::ExtraProofs(13))
+        );
+        assert_eq!(
+            verify_text(blocks, &certificate.replace("window 5", "window 4")),
+            Err(CertificateError::WindowSizeMismatch {
+                window_size: 4,
+                expected: 5
+            })
+        );
+        assert_eq!(
+            verify_text(blocks, "window 5\n"),
+            Err(CertificateError::InvalidMagic)
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::mine::{Block, Mine};
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+/// First line of a checkpoint.
+const MAGIC: &str = "tmmt checkpoint 1";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CheckpointError {
+    #[error("Input is not a checkpoint.")]
+    InvalidMagic,
+    #[error("Checkpoint is for a validation window of {window_size} blocks, expected {expected}.")]
+    WindowSizeMismatch { window_size: usize, expected: usize },
+    #[error("Invalid checkpoint line {line}: {reason}.")]
+    InvalidLine { line: usize, reason: String },
+    #[error("Checkpoint ends after {0} lines.")]
+    Truncated(usize),
+    #[error("Failed to read or write a checkpoint: {0}.")]
+    Io(io::ErrorKind),
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error.kind())
+    }
+}
+
+/// State needed to resume validation with correct block numbers, see [Mine::resume].
+///
+/// Stored as text, one value per line:
+///
+/// ```text
+/// tmmt checkpoint 1
+/// window 5
+/// blocks 10000000014
+/// 95
+/// 102
+/// 117
+/// 150
+/// 182
+/// ```
+///
+/// The validation window size and the number of blocks added to the mine, followed by
+/// the validation window in arrival order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint<const VALIDATION_WINDOW_SIZE: usize, B> {
+    /// Number of blocks added to the mine, initialization blocks included.
+    pub total_blocks: u64,
+    /// Blocks used for validation, in arrival order.
+    pub validation_blocks: [B; VALIDATION_WINDOW_SIZE],
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Checkpoint<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Clone,
+{
+    /// Checkpoint of the current state of the `mine`.
+    pub fn of<M: Mine<VALIDATION_WINDOW_SIZE, B>>(mine: &M) -> Self {
+        let validation_blocks = mine
+            .validation_blocks()
+            .cloned()
+            .collect::<Vec<B>>()
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("the validation window is always full"));
+
+        Self {
+            total_blocks: mine.total_blocks(),
+            validation_blocks,
+        }
+    }
+
+    /// Recreate a mine from the checkpoint. The next block is validated as block
+    /// number `total_blocks + 1`.
+    /// # Panics
+    /// If `total_blocks` is less than [VALIDATION_WINDOW_SIZE].
+    ///
+    /// [VALIDATION_WINDOW_SIZE]: Checkpoint<VALIDATION_WINDOW_SIZE>
+    pub fn resume<M: Mine<VALIDATION_WINDOW_SIZE, B>>(self) -> M {
+        M::resume(self.validation_blocks, self.total_blocks)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()>
+    where
+        B: Display,
+    {
+        writeln!(writer, "{MAGIC}")?;
+        writeln!(writer, "window {VALIDATION_WINDOW_SIZE}")?;
+        writeln!(writer, "blocks {}", self.total_blocks)?;
+        for block in &self.validation_blocks {
+            writeln!(writer, "{block}")?;
+        }
+        writer.flush()
+    }
+
+    /// Read a checkpoint written by [Checkpoint::write_to].
+    pub fn read_from(reader: impl BufRead) -> Result<Self, CheckpointError>
+    where
+        B: FromStr,
+        B::Err: Display,
+    {
+        let mut lines = reader.lines();
+        let mut line = 0;
+        let mut next_line = || {
+            line += 1;
+            match lines.next() {
+                Some(text) => Ok((line, text?)),
+                None => Err(CheckpointError::Truncated(line - 1)),
+            }
+        };
+
+        let (_, magic) = next_line()?;
+        if magic.trim_end() != MAGIC {
+            return Err(CheckpointError::InvalidMagic);
+        }
+
+        let window_size: usize = parse_field(next_line()?, "window")?;
+        if window_size != VALIDATION_WINDOW_SIZE {
+            return Err(CheckpointError::WindowSizeMismatch {
+                window_size,
+                expected: VALIDATION_WINDOW_SIZE,
+            });
+        }
+
+        let (line, total_blocks) = next_line()?;
+        let total_blocks: u64 = parse_field((line, total_blocks), "blocks")?;
+        if total_blocks < VALIDATION_WINDOW_SIZE as u64 {
+            return Err(CheckpointError::InvalidLine {
+                line,
+                reason: "fewer blocks than the validation window".to_owned(),
+            });
+        }
+
+        let mut validation_blocks = Vec::with_capacity(VALIDATION_WINDOW_SIZE);
+        for _ in 0..VALIDATION_WINDOW_SIZE {
+            let (line, block) = next_line()?;
+            let block =
+                block
+                    .trim()
+                    .parse()
+                    .map_err(|error: B::Err| CheckpointError::InvalidLine {
+                        line,
+                        reason: error.to_string(),
+                    })?;
+            validation_blocks.push(block);
+        }
+
+        Ok(Self {
+            total_blocks,
+            validation_blocks: validation_blocks
+                .try_into()
+                .unwrap_or_else(|_| unreachable!("the validation window is full")),
+        })
+    }
+}
+
+/// Value of a `name value` line.
+fn parse_field<T: FromStr>(
+    (line, text): (usize, String),
+    name: &str,
+) -> Result<T, CheckpointError> {
+    text.trim()
+        .strip_prefix(name)
+        .filter(|value| value.starts_with(' '))
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or_else(|| CheckpointError::InvalidLine {
+            line,
+            reason: format!("expected `{name} <number>`"),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::MineError,
+        test_data::{EXAMPLE, FIRST_INVALID_BLOCK_NUMBER},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    #[test]
+    fn resume() {
+        let mut mine = HashMine::<5, u64>::try_new(&EXAMPLE[..5]).unwrap();
+        mine.try_extend(EXAMPLE[5..10].iter().copied()).unwrap();
+
+        let mut bytes = Vec::new();
+        Checkpoint::of(&mine).write_to(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.clone()).unwrap(),
+            "tmmt checkpoint 1\nwindow 5\nblocks 10\n40\n62\n55\n65\n95\n"
+        );
+
+        let checkpoint = Checkpoint::<5, u64>::read_from(&bytes[..]).unwrap();
+        let mut mine: TwoPtrMine<5, u64> = checkpoint.resume();
+        assert_eq!(mine.total_blocks(), 10);
+        assert_eq!(
+            mine.try_extend(EXAMPLE[10..].iter().copied()),
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+        );
+    }
+
+    #[test]
+    fn absolute_block_numbers() {
+        let checkpoint = Checkpoint {
+            total_blocks: 10_000_000_000,
+            validation_blocks: [1u64, 2, 3],
+        };
+        let mut mine: HashMine<3, u64> = checkpoint.clone().resume();
+        assert_eq!(mine.try_extend_one(5), Ok(()));
+        assert_eq!(
+            mine.try_extend_one(100),
+            Err(MineError::InvalidBlock(100, 10_000_000_002))
+        );
+        assert_eq!(Checkpoint::of(&mine).total_blocks, 10_000_000_001);
+
+        let mut bytes = Vec::new();
+        checkpoint.write_to(&mut bytes).unwrap();
+        assert_eq!(Checkpoint::read_from(&bytes[..]), Ok(checkpoint));
+    }
+
+    #[test]
+    fn errors() {
+        let read = |text: &str| Checkpoint::<3, u64>::read_from(text.as_bytes());
+
+        assert_eq!(read("1\n2\n3\n"), Err(CheckpointError::InvalidMagic));
+        assert_eq!(
+            read("tmmt checkpoint 1\nwindow 5\n"),
+            Err(CheckpointError::WindowSizeMismatch {
+                window_size: 5,
+                expected: 3
+            })
+        );
+        assert_eq!(
+            read("tmmt checkpoint 1\nwindow 3\nblocks x\n"),
+            Err(CheckpointError::InvalidLine {
+                line: 3,
+                reason: "expected `blocks <number>`".to_owned()
+            })
+        );
+        assert_eq!(
+            read("tmmt checkpoint 1\nwindow 3\nblocks 2\n"),
+            Err(CheckpointError::InvalidLine {
+                line: 3,
+                reason: "fewer blocks than the validation window".to_owned()
+            })
+        );
+        assert_eq!(
+            read("tmmt checkpoint 1\nwindow 3\nblocks 3\n1\n-2\n3\n"),
+            Err(CheckpointError::InvalidLine {
+                line: 5,
+                reason: "invalid digit found in string".to_owned()
+            })
+        );
+        assert_eq!(
+            read("tmmt checkpoint 1\nwindow 3\nblocks 3\n1\n2\n"),
+            Err(CheckpointError::Truncated(5))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "A mine holds at least 3 blocks")]
+    fn too_few_blocks() {
+        let _: TwoPtrMine<3, u64> = Checkpoint {
+            total_blocks: 2,
+            validation_blocks: [1, 2, 3],
+        }
+        .resume();
+    }
+}
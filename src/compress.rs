@@ -0,0 +1,335 @@
+use crate::{
+    mine::{Block, Mine},
+    parse::{self, BlockReader, IngestError},
+};
+
+use std::{
+    cell::Cell,
+    fmt::{self, Display},
+    io::{self, BufRead, BufReader, Read},
+    rc::Rc,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Requires the `gzip` feature.
+    Gzip,
+    /// Requires the `zstd` feature.
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression from the magic bytes at the start of the input.
+    pub fn detect(start: &[u8]) -> Self {
+        if start.starts_with(&GZIP_MAGIC) {
+            Self::Gzip
+        } else if start.starts_with(&ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Error for input compressed with a format whose feature is disabled.
+    #[cfg(not(all(feature = "gzip", feature = "zstd")))]
+    fn unsupported(self) -> io::Error {
+        let feature = match self {
+            Self::None => unreachable!("uncompressed input is always supported"),
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        };
+
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{feature} compressed input requires the `{feature}` feature"),
+        )
+    }
+}
+
+/// Reader decompressing its input as it streams. The compression is detected from the
+/// magic bytes at the start of the input, uncompressed input is passed through.
+///
+/// Keeps track of the bytes of compressed input consumed, see
+/// [DecompressedReader::compressed_offset].
+pub struct DecompressedReader<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    compression: Compression,
+    compressed_offset: Rc<Cell<u64>>,
+}
+
+impl<'a> DecompressedReader<'a> {
+    /// Detect the compression of the `reader`. Fails if the input can't be read, or it's
+    /// compressed with a format whose feature is disabled.
+    pub fn new(mut reader: impl Read + 'a) -> io::Result<Self> {
+        let mut magic = [0; ZSTD_MAGIC.len()];
+        let mut magic_len = 0;
+        while magic_len < magic.len() {
+            match reader.read(&mut magic[magic_len..]) {
+                Ok(0) => break,
+                Ok(read) => magic_len += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        let compression = Compression::detect(&magic[..magic_len]);
+        let compressed_offset = Rc::new(Cell::new(0));
+        // put the magic bytes back in front of the input
+        let input = io::Cursor::new(magic).take(magic_len as u64).chain(reader);
+        let input = CountingReader {
+            reader: BufReader::new(input),
+            count: Rc::clone(&compressed_offset),
+        };
+
+        // the decoders read from the buffer of the input, so only the bytes they consume
+        // are counted, not the ones buffered ahead
+        let reader: Box<dyn BufRead + 'a> = match compression {
+            Compression::None => Box::new(input),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let decoder = flate2::bufread::MultiGzDecoder::new(input);
+                Box::new(BufReader::new(decoder))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let decoder = zstd::stream::read::Decoder::with_buffer(input)?;
+                Box::new(BufReader::new(decoder))
+            }
+            #[cfg(not(feature = "gzip"))]
+            Compression::Gzip => return Err(compression.unsupported()),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => return Err(compression.unsupported()),
+        };
+
+        Ok(Self {
+            reader,
+            compression,
+            compressed_offset,
+        })
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Number of bytes of compressed input consumed so far. For uncompressed input this is
+    /// the number of bytes read. Compressed input is decompressed ahead of the output read,
+    /// so this is at least past the bytes holding that output, usually by less than a block
+    /// of the compression format.
+    pub fn compressed_offset(&self) -> u64 {
+        self.compressed_offset.get()
+    }
+
+    /// Same as [Ingest::try_create_and_ingest](crate::parse::Ingest::try_create_and_ingest),
+    /// reporting how far validation got on failure.
+    pub fn try_create_and_ingest<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        self,
+    ) -> Result<(), DecompressIngestError<M::Error>>
+    where
+        B: Block + FromStr,
+        B::Err: Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let compressed_offset = Rc::clone(&self.compressed_offset);
+        let mut blocks = BlockReader::new(self);
+
+        parse::create_and_extend_from_blocks::<VALIDATION_WINDOW_SIZE, B, M>(&mut blocks).map_err(
+            |error| DecompressIngestError {
+                error,
+                offset: blocks.byte_offset(),
+                compressed_offset: compressed_offset.get(),
+            },
+        )
+    }
+}
+
+impl fmt::Debug for DecompressedReader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecompressedReader")
+            .field("compression", &self.compression)
+            .field("compressed_offset", &self.compressed_offset())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Read for DecompressedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for DecompressedReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// Error of feeding possibly compressed input to a [Mine].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "{error} Validation stopped at byte {offset} of the decompressed input, \
+    after consuming {compressed_offset} bytes of input."
+)]
+pub struct DecompressIngestError<E> {
+    pub error: IngestError<E>,
+    /// Bytes of decompressed input read, up to the end of the line of the failure.
+    pub offset: u64,
+    /// Bytes of input consumed, see [DecompressedReader::compressed_offset]. Same as
+    /// `offset` for uncompressed input.
+    pub compressed_offset: u64,
+}
+
+/// Counts the bytes consumed from the `reader`, excluding the ones only buffered.
+struct CountingReader<R> {
+    reader: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.count.set(self.count.get() + amt as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{hash_mine::HashMine, mine::MineError};
+
+    const EXAMPLE: &str = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n";
+
+    fn invalid_example_block<E>(offset: u64, compressed_offset: u64) -> DecompressIngestError<E>
+    where
+        E: From<MineError<5, u64>>,
+    {
+        DecompressIngestError {
+            error: IngestError::Mine {
+                line: Some(15),
+                source: MineError::InvalidBlock(127, 15).into(),
+            },
+            offset,
+            compressed_offset,
+        }
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 8, 0]), Compression::Gzip);
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::detect(b"35\n20"), Compression::None);
+        assert_eq!(Compression::detect(&[0x28, 0xb5]), Compression::None);
+        assert_eq!(Compression::detect(b""), Compression::None);
+    }
+
+    #[test]
+    fn uncompressed() {
+        let reader = DecompressedReader::new(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(reader.compression(), Compression::None);
+        assert_eq!(
+            reader.try_create_and_ingest::<5, u64, HashMine<5, u64>>(),
+            Err(invalid_example_block(50, 50))
+        );
+
+        let mut reader = DecompressedReader::new("1\n2".as_bytes()).unwrap();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "1\n2");
+        assert_eq!(reader.compressed_offset(), 3);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        use crate::two_ptr_mine::TwoPtrMine;
+        use flate2::{write::GzEncoder, Compression as Level};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(EXAMPLE.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let reader = DecompressedReader::new(&compressed[..]).unwrap();
+        assert_eq!(reader.compression(), Compression::Gzip);
+        assert_eq!(
+            reader.try_create_and_ingest::<5, u64, TwoPtrMine<5, u64>>(),
+            // the 8 bytes trailer isn't consumed yet
+            Err(invalid_example_block(50, compressed.len() as u64 - 8))
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_offset() {
+        use flate2::{write::GzEncoder, Compression as Level};
+        use std::io::Write;
+
+        // blocks after the invalid one aren't consumed
+        let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(EXAMPLE.as_bytes()).unwrap();
+        for i in 0..100_000u64 {
+            writeln!(encoder, "{}", i.wrapping_mul(0x9e37_79b9_7f4a_7c15)).unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+
+        let reader = DecompressedReader::new(&compressed[..]).unwrap();
+        let error = reader
+            .try_create_and_ingest::<5, u64, HashMine<5, u64>>()
+            .unwrap_err();
+        assert_eq!(error.offset, 50);
+        assert!(error.compressed_offset < compressed.len() as u64 / 10);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let compressed = zstd::encode_all(EXAMPLE.as_bytes(), 0).unwrap();
+
+        let reader = DecompressedReader::new(&compressed[..]).unwrap();
+        assert_eq!(reader.compression(), Compression::Zstd);
+        assert_eq!(
+            reader.try_create_and_ingest::<5, u64, HashMine<5, u64>>(),
+            Err(invalid_example_block(50, compressed.len() as u64))
+        );
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn unsupported() {
+        let error = DecompressedReader::new(&[0x1f, 0x8b, 8, 0][..])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,404 @@
+use crate::mine::{Block, Mine};
+
+use std::{fmt::Display, io::Read, marker::PhantomData, str::FromStr};
+
+use csv::{ReaderBuilder, StringRecord, Trim};
+use thiserror::Error;
+
+/// Column of a CSV input, by header name or by index starting from 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_owned())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CsvError {
+    #[error("Column {0:?} is not in the header.")]
+    MissingColumn(String),
+    #[error("Line {line} has no column {column}.")]
+    ShortRow { line: u64, column: usize },
+    #[error("Invalid block value {value:?} on line {line}: {reason}.")]
+    InvalidBlock {
+        line: u64,
+        value: String,
+        reason: String,
+    },
+    #[error("Failed to read CSV input: {0}")]
+    Read(#[from] csv::Error),
+}
+
+/// Error of feeding CSV input to a [Mine].
+#[derive(Debug, Error)]
+pub enum CsvIngestError<E> {
+    #[error(transparent)]
+    Csv(#[from] CsvError),
+    /// Failure reported by the [Mine]. `line`, `id` and `record` identify the row of the
+    /// rejected block, they are `None` if the input ended early. The `record` holds all the
+    /// fields of the row and its byte offset.
+    #[error(
+        "{source} Line: {}, id: {}.",
+        line.map_or("end of input".to_owned(), |line| line.to_string()),
+        id.as_deref().unwrap_or("none")
+    )]
+    Mine {
+        line: Option<u64>,
+        id: Option<String>,
+        record: Option<StringRecord>,
+        source: E,
+    },
+}
+
+/// Describes the layout of a CSV or TSV input holding blocks.
+///
+/// Leading and trailing whitespace of fields is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvFormat {
+    delimiter: u8,
+    has_headers: bool,
+    value_column: Column,
+    id_column: Option<Column>,
+}
+
+impl CsvFormat {
+    /// Comma separated input with a header, blocks are in the `value_column`.
+    pub fn csv(value_column: impl Into<Column>) -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+            value_column: value_column.into(),
+            id_column: None,
+        }
+    }
+
+    /// Tab separated input with a header, blocks are in the `value_column`.
+    pub fn tsv(value_column: impl Into<Column>) -> Self {
+        Self {
+            delimiter: b'\t',
+            ..Self::csv(value_column)
+        }
+    }
+
+    /// Input without a header. Columns can only be picked by index.
+    pub fn without_headers(self) -> Self {
+        Self {
+            has_headers: false,
+            ..self
+        }
+    }
+
+    /// Report the `id_column` of rejected blocks.
+    pub fn with_id_column(self, id_column: impl Into<Column>) -> Self {
+        Self {
+            id_column: Some(id_column.into()),
+            ..self
+        }
+    }
+
+    /// Iterator over the rows of the `reader`.
+    /// Fails if the header can't be read, or a column is not in it.
+    pub fn rows<R: Read, B>(&self, reader: R) -> Result<Rows<R, B>, CsvError> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .trim(Trim::All)
+            .flexible(true)
+            .from_reader(reader);
+
+        let headers = if self.has_headers {
+            Some(reader.headers()?.clone())
+        } else {
+            None
+        };
+        let index = |column: &Column| match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => headers
+                .as_ref()
+                .and_then(|headers| headers.iter().position(|header| header == name))
+                .ok_or_else(|| CsvError::MissingColumn(name.clone())),
+        };
+
+        Ok(Rows {
+            value_column: index(&self.value_column)?,
+            id_column: self.id_column.as_ref().map(index).transpose()?,
+            reader,
+            _block: PhantomData,
+        })
+    }
+
+    /// Same as [Mine::try_create_and_extend] with the blocks read from the `reader`.
+    pub fn try_create_and_ingest<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &self,
+        reader: impl Read,
+    ) -> Result<(), CsvIngestError<M::Error>>
+    where
+        B: Block + FromStr,
+        B::Err: Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let mut rows = self.rows(reader)?;
+
+        let initialization_blocks = rows
+            .by_ref()
+            .take(VALIDATION_WINDOW_SIZE)
+            .map(|row| row.map(|row| row.value))
+            .collect::<Result<Vec<B>, _>>()?;
+
+        let mut mine =
+            M::try_new(initialization_blocks).map_err(|source| CsvIngestError::Mine {
+                line: None,
+                id: None,
+                record: None,
+                source,
+            })?;
+
+        for row in rows {
+            let Row {
+                value,
+                line,
+                id,
+                record,
+            } = row?;
+
+            mine.try_extend_one(value)
+                .map_err(|source| CsvIngestError::Mine {
+                    line: Some(line),
+                    id,
+                    record: Some(record),
+                    source,
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Block read from a CSV input, with the rest of its row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row<B> {
+    pub value: B,
+    /// Line of the row, starting from 1.
+    pub line: u64,
+    /// Value of the id column, if one is set.
+    pub id: Option<String>,
+    /// All the fields of the row.
+    pub record: StringRecord,
+}
+
+/// Iterator over the [Rows](Row) of a CSV input, see [CsvFormat::rows].
+#[derive(Debug)]
+pub struct Rows<R, B> {
+    reader: csv::Reader<R>,
+    value_column: usize,
+    id_column: Option<usize>,
+    _block: PhantomData<fn() -> B>,
+}
+
+impl<R, B> Iterator for Rows<R, B>
+where
+    R: Read,
+    B: FromStr,
+    B::Err: Display,
+{
+    type Item = Result<Row<B>, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = StringRecord::new();
+        match self.reader.read_record(&mut record) {
+            Ok(true) => Some(self.parse_row(record)),
+            Ok(false) => None,
+            Err(error) => Some(Err(error.into())),
+        }
+    }
+}
+
+impl<R, B> Rows<R, B>
+where
+    B: FromStr,
+    B::Err: Display,
+{
+    fn parse_row(&self, record: StringRecord) -> Result<Row<B>, CsvError> {
+        let line = record.position().map_or(0, |position| position.line());
+        let field = |column| {
+            record
+                .get(column)
+                .ok_or(CsvError::ShortRow { line, column })
+        };
+
+        let value = field(self.value_column)?;
+        let value = value
+            .parse()
+            .map_err(|reason: B::Err| CsvError::InvalidBlock {
+                line,
+                value: value.to_owned(),
+                reason: reason.to_string(),
+            })?;
+        let id = self
+            .id_column
+            .map(|column| field(column).map(str::to_owned))
+            .transpose()?;
+
+        Ok(Row {
+            value,
+            line,
+            id,
+            record,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{hash_mine::HashMine, mine::MineError, two_ptr_mine::TwoPtrMine};
+
+    const EXAMPLE: &str = "\
+id,timestamp,value
+a1,2023-09-01T00:00:00Z,35
+a2,2023-09-01T00:01:00Z,20
+a3,2023-09-01T00:02:00Z,15
+a4,2023-09-01T00:03:00Z,25
+a5,2023-09-01T00:04:00Z,47
+a6,2023-09-01T00:05:00Z,40
+a7,2023-09-01T00:06:00Z,62
+a8,2023-09-01T00:07:00Z,55
+a9,2023-09-01T00:08:00Z,65
+a10,2023-09-01T00:09:00Z,95
+a11,2023-09-01T00:10:00Z,102
+a12,2023-09-01T00:11:00Z,117
+a13,2023-09-01T00:12:00Z,150
+a14,2023-09-01T00:13:00Z,182
+a15,2023-09-01T00:14:00Z,127
+";
+
+    #[test]
+    fn rows() {
+        let format = CsvFormat::csv("value").with_id_column("id");
+        let rows: Vec<Row<u64>> = format
+            .rows(EXAMPLE.as_bytes())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(rows.len(), 15);
+        assert_eq!(rows[6].value, 62);
+        assert_eq!(rows[6].line, 8);
+        assert_eq!(rows[6].id.as_deref(), Some("a7"));
+        assert_eq!(&rows[6].record[1], "2023-09-01T00:06:00Z");
+
+        let tsv = "5\t 1\n6\t2 \n";
+        let format = CsvFormat::tsv(1).without_headers().with_id_column(0);
+        let rows: Vec<Row<u64>> = format
+            .rows(tsv.as_bytes())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.value, row.id.as_deref()))
+                .collect::<Vec<_>>(),
+            [(1, Some("5")), (2, Some("6"))]
+        );
+    }
+
+    #[test]
+    fn ingest() {
+        let format = CsvFormat::csv("value").with_id_column("id");
+        let Err(CsvIngestError::Mine {
+            line,
+            id,
+            record: Some(record),
+            source,
+        }) = format.try_create_and_ingest::<5, u64, HashMine<5, u64>>(EXAMPLE.as_bytes())
+        else {
+            panic!("expected a rejected block");
+        };
+        assert_eq!(line, Some(16));
+        assert_eq!(id.as_deref(), Some("a15"));
+        assert_eq!(source, MineError::InvalidBlock(127, 15));
+        assert_eq!(&record[1], "2023-09-01T00:14:00Z");
+        assert_eq!(
+            record.position().map(|position| position.byte()),
+            EXAMPLE.find("a15").map(|offset| offset as u64)
+        );
+
+        let format = CsvFormat::csv(2);
+        assert!(matches!(
+            format.try_create_and_ingest::<5, u64, TwoPtrMine<5, u64>>(EXAMPLE.as_bytes()),
+            Err(CsvIngestError::Mine {
+                line: Some(16),
+                id: None,
+                source: MineError::InvalidBlock(127, 15),
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            CsvFormat::csv("value")
+                .try_create_and_ingest::<5, u64, TwoPtrMine<5, u64>>("value\n1\n".as_bytes()),
+            Err(CsvIngestError::Mine {
+                line: None,
+                record: None,
+                source: MineError::InvalidInitializationSequenceLen,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            CsvFormat::csv("amount").rows::<_, u64>(EXAMPLE.as_bytes()),
+            Err(CsvError::MissingColumn(column)) if column == "amount"
+        ));
+        assert!(matches!(
+            CsvFormat::csv(0)
+                .without_headers()
+                .with_id_column("id")
+                .rows::<_, u64>(EXAMPLE.as_bytes()),
+            Err(CsvError::MissingColumn(column)) if column == "id"
+        ));
+
+        let input = "id,value\na,1\nb\nc,x\n";
+        let rows: Vec<_> = CsvFormat::csv("value")
+            .rows::<_, u64>(input.as_bytes())
+            .unwrap()
+            .collect();
+        assert_eq!(rows[0].as_ref().map(|row| row.value).ok(), Some(1));
+        assert!(matches!(
+            rows[1],
+            Err(CsvError::ShortRow { line: 3, column: 1 })
+        ));
+        assert!(matches!(
+            &rows[2],
+            Err(CsvError::InvalidBlock { line: 4, value, reason })
+                if value == "x" && reason == "invalid digit found in string"
+        ));
+
+        // the kind and position of read errors are kept
+        let Some(Err(CsvError::Read(error))) = CsvFormat::csv("value")
+            .rows::<_, u64>(&b"value\n1\n\xff\n"[..])
+            .unwrap()
+            .nth(1)
+        else {
+            panic!("expected a read error");
+        };
+        assert!(matches!(error.kind(), csv::ErrorKind::Utf8 { .. }));
+        assert_eq!(error.position().map(|position| position.line()), Some(3));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::mine::{Block, Mine, MineError};
+
+/// Object safe companion of [Mine]. Has a blanket implementation for every [Mine],
+/// so different implementations can be stored side by side as `Box<dyn DynMine<..>>`.
+///
+/// Constructors are not a part of this trait. Create a concrete mine
+/// and box it afterwards. Error type `E` is the [Mine::Error] of the boxed mines.
+pub trait DynMine<
+    const VALIDATION_WINDOW_SIZE: usize,
+    B: Block + Clone,
+    E = MineError<VALIDATION_WINDOW_SIZE, B>,
+>
+{
+    /// See [Mine::try_extend_one].
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), E>;
+
+    /// See [Mine::try_extend_one_justified].
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, E>;
+
+    /// Try to extend the mine with all the `blocks` in the slice.
+    /// See [Mine::try_extend].
+    fn try_extend_slice(&mut self, blocks: &[B]) -> Result<(), E>;
+
+    /// See [Mine::peek].
+    fn peek(&self, new_block: &B) -> Result<(), E>;
+
+    /// See [Mine::validation_blocks].
+    fn validation_blocks<'a>(&'a self) -> Box<dyn ExactSizeIterator<Item = &'a B> + 'a>
+    where
+        B: 'a;
+
+    /// See [Mine::total_blocks].
+    fn total_blocks(&self) -> u64;
+
+    /// See [Mine::ordered_validation_blocks].
+    fn ordered_validation_blocks(&self) -> Option<&[B]>;
+
+    /// See [Mine::distinct_pair_sums].
+    fn distinct_pair_sums(&self) -> Option<usize>;
+
+    /// See [Mine::justifying_pair].
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)>;
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B, M> DynMine<VALIDATION_WINDOW_SIZE, B, M::Error> for M
+where
+    B: Block + Clone,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), M::Error> {
+        Mine::try_extend_one(self, new_block)
+    }
+
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, M::Error> {
+        Mine::try_extend_one_justified(self, new_block)
+    }
+
+    fn try_extend_slice(&mut self, blocks: &[B]) -> Result<(), M::Error> {
+        Mine::try_extend(self, blocks.iter().cloned())
+    }
+
+    fn peek(&self, new_block: &B) -> Result<(), M::Error> {
+        Mine::peek(self, new_block)
+    }
+
+    fn validation_blocks<'a>(&'a self) -> Box<dyn ExactSizeIterator<Item = &'a B> + 'a>
+    where
+        B: 'a,
+    {
+        Box::new(Mine::validation_blocks(self))
+    }
+
+    fn total_blocks(&self) -> u64 {
+        Mine::total_blocks(self)
+    }
+
+    fn ordered_validation_blocks(&self) -> Option<&[B]> {
+        Mine::ordered_validation_blocks(self)
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        Mine::distinct_pair_sums(self)
+    }
+
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)> {
+        Mine::justifying_pair(self, new_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        adaptive_mine::{AdaptiveMine, Strategy},
+        hash_mine::HashMine,
+        mine::MineError,
+        test_data::{example, FIRST_INVALID_BLOCK_NUMBER},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn registry() -> Vec<Box<dyn DynMine<5, u128>>> {
+        let initial_blocks: [u128; 5] = example::<u128>()[..5].try_into().unwrap();
+
+        vec![
+            Box::new(HashMine::new(initial_blocks)),
+            Box::new(TwoPtrMine::new(initial_blocks)),
+            Box::new(AdaptiveMine::with_strategy(initial_blocks, Strategy::Hash)),
+        ]
+    }
+
+    #[test]
+    fn heterogeneous_registry() {
+        for mut mine in registry() {
+            assert_eq!(mine.total_blocks(), 5);
+            assert_eq!(
+                mine.validation_blocks().copied().collect::<Vec<_>>(),
+                [35, 20, 15, 25, 47]
+            );
+
+            assert_eq!(mine.peek(&40), Ok(()));
+            assert_eq!(mine.peek(&1), Err(MineError::InvalidBlock(1, 6)));
+            assert_eq!(mine.total_blocks(), 5, "Peeking must not extend the mine");
+
+            assert_eq!(mine.try_extend_one(40), Ok(()));
+            assert_eq!(
+                mine.try_extend_slice(&example::<u128>()[6..]),
+                Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+            );
+
+            assert_eq!(mine.total_blocks(), 14);
+            assert_eq!(mine.validation_blocks().len(), 5);
+            assert_eq!(
+                mine.validation_blocks().copied().collect::<Vec<_>>(),
+                [95, 102, 117, 150, 182]
+            );
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use crate::mine::{Block, Mine, MineError};
+
+use std::{collections::BTreeMap, fmt, hash::Hash, ops::Add};
+
+use thiserror::Error;
+
+/// [Mine] on persistent data structures. Cloning it, see [ForkMine::fork], is constant
+/// time and the clones share their storage until they diverge.
+/// # Performance
+/// - Validation is as in [HashMine](crate::hash_mine::HashMine), with logarithmic
+///   lookups and updates.
+/// - Each accepted block copies O(VALIDATION_WINDOW_SIZE) paths of the shared storage.
+#[derive(Clone, Debug)]
+pub struct ForkMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone> {
+    /// Holds [VALIDATION_WINDOW_SIZE] blocks used for validation.
+    ///
+    /// [VALIDATION_WINDOW_SIZE]: ForkMine<VALIDATION_WINDOW_SIZE>
+    validation_blocks: im::Vector<B>,
+    /// Number of pairs of [validation_blocks](Self::validation_blocks) with the key as sum.
+    block_pair_sums: im::HashMap<B, usize>,
+    total_blocks: u64,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> ForkMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    /// Independent copy of the mine, sharing its storage.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    fn insert_sum(&mut self, sum: B) {
+        *self.block_pair_sums.entry(sum).or_insert(0) += 1;
+    }
+
+    fn remove_sum(&mut self, sum: B) {
+        if let im::hashmap::Entry::Occupied(mut entry) = self.block_pair_sums.entry(sum) {
+            if *entry.get() == 1 {
+                entry.remove();
+            } else {
+                *entry.get_mut() -= 1;
+            }
+        }
+    }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
+    for ForkMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    type Error = MineError<VALIDATION_WINDOW_SIZE, B>;
+
+    /// Recreate a mine from its `validation_blocks`, see [Mine::resume].
+    /// # Performance
+    /// O(VALIDATION_WINDOW_SIZE<sup>2</sup>), same as creating a
+    /// [HashMine](crate::hash_mine::HashMine).
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        assert!(
+            total_blocks >= VALIDATION_WINDOW_SIZE as u64,
+            "A mine holds at least {VALIDATION_WINDOW_SIZE} blocks, not {total_blocks}."
+        );
+
+        let mut mine = Self {
+            validation_blocks: validation_blocks.into_iter().collect(),
+            block_pair_sums: im::HashMap::new(),
+            total_blocks,
+        };
+        for i in 0..VALIDATION_WINDOW_SIZE {
+            for j in i + 1..VALIDATION_WINDOW_SIZE {
+                let sum = &mine.validation_blocks[i] + &mine.validation_blocks[j];
+                mine.insert_sum(sum);
+            }
+        }
+        mine
+    }
+
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error> {
+        self.peek(&new_block)?;
+
+        let old_block = self
+            .validation_blocks
+            .pop_front()
+            .expect("Mine always has VALIDATION_WINDOW_SIZE blocks");
+
+        for i in 0..self.validation_blocks.len() {
+            let block = &self.validation_blocks[i];
+            let (old_sum, new_sum) = (&old_block + block, &new_block + block);
+            self.remove_sum(old_sum);
+            self.insert_sum(new_sum);
+        }
+
+        self.validation_blocks.push_back(new_block);
+        self.total_blocks += 1;
+
+        Ok(())
+    }
+
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error> {
+        if self.block_pair_sums.contains_key(new_block) {
+            Ok(())
+        } else {
+            Err(MineError::InvalidBlock(
+                new_block.clone(),
+                self.total_blocks + 1,
+            ))
+        }
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a,
+    {
+        self.validation_blocks.iter()
+    }
+
+    fn total_blocks(&self) -> u64 {
+        self.total_blocks
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        Some(self.block_pair_sums.len())
+    }
+}
+
+/// Identifies a branch in [Branches].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BranchId(u64);
+
+impl BranchId {
+    /// The branch [Branches] start with.
+    pub const ROOT: Self = Self(0);
+}
+
+impl fmt::Display for BranchId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BranchError<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
+    #[error("There is no branch {0}.")]
+    UnknownBranch(BranchId),
+    #[error("Branch {branch} is invalid since block number {block_number}.")]
+    InvalidBranch { branch: BranchId, block_number: u64 },
+    #[error(transparent)]
+    Mine(#[from] MineError<VALIDATION_WINDOW_SIZE, B>),
+}
+
+/// State of a branch in [Branches].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BranchStatus {
+    /// Branch this one was forked from, `None` for [BranchId::ROOT].
+    pub parent: Option<BranchId>,
+    /// Number of blocks in the branch, initialization blocks and blocks inherited from
+    /// the parent included.
+    pub total_blocks: u64,
+    /// Number of the first block the branch rejected.
+    pub invalid_block: Option<u64>,
+}
+
+impl BranchStatus {
+    pub fn is_valid(&self) -> bool {
+        self.invalid_block.is_none()
+    }
+}
+
+#[derive(Debug)]
+struct Branch<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone> {
+    mine: ForkMine<VALIDATION_WINDOW_SIZE, B>,
+    parent: Option<BranchId>,
+    invalid_block: Option<u64>,
+}
+
+/// Competing continuations of a stream, each validated by its own [ForkMine].
+///
+/// A branch stops at its first invalid block, later blocks are refused with
+/// [BranchError::InvalidBranch]. Forking is cheap, so is keeping many branches that
+/// share most of their history.
+#[derive(Debug)]
+pub struct Branches<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone> {
+    branches: BTreeMap<BranchId, Branch<VALIDATION_WINDOW_SIZE, B>>,
+    next_id: u64,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Branches<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    /// Start with `mine` as the [BranchId::ROOT] branch.
+    pub fn new(mine: ForkMine<VALIDATION_WINDOW_SIZE, B>) -> Self {
+        let root = Branch {
+            mine,
+            parent: None,
+            invalid_block: None,
+        };
+
+        Self {
+            branches: BTreeMap::from([(BranchId::ROOT, root)]),
+            next_id: 1,
+        }
+    }
+
+    /// Start a new branch from the current state of `from`, invalid or not.
+    pub fn fork(
+        &mut self,
+        from: BranchId,
+    ) -> Result<BranchId, BranchError<VALIDATION_WINDOW_SIZE, B>> {
+        let parent = self.branch(from)?;
+        let branch = Branch {
+            mine: parent.mine.fork(),
+            parent: Some(from),
+            invalid_block: parent.invalid_block,
+        };
+
+        let id = BranchId(self.next_id);
+        self.next_id += 1;
+        self.branches.insert(id, branch);
+        Ok(id)
+    }
+
+    /// Validate `new_block` on `branch`. A rejected block invalidates the branch.
+    pub fn try_extend_one(
+        &mut self,
+        branch: BranchId,
+        new_block: B,
+    ) -> Result<(), BranchError<VALIDATION_WINDOW_SIZE, B>> {
+        let state = self
+            .branches
+            .get_mut(&branch)
+            .ok_or(BranchError::UnknownBranch(branch))?;
+        if let Some(block_number) = state.invalid_block {
+            return Err(BranchError::InvalidBranch {
+                branch,
+                block_number,
+            });
+        }
+
+        state.mine.try_extend_one(new_block).map_err(|error| {
+            state.invalid_block = Some(state.mine.total_blocks() + 1);
+            error.into()
+        })
+    }
+
+    /// Validate all the `blocks` on `branch`, stopping at the first invalid one.
+    pub fn try_extend(
+        &mut self,
+        branch: BranchId,
+        blocks: impl IntoIterator<Item = B>,
+    ) -> Result<(), BranchError<VALIDATION_WINDOW_SIZE, B>> {
+        blocks
+            .into_iter()
+            .try_for_each(|block| self.try_extend_one(branch, block))
+    }
+
+    pub fn mine(&self, branch: BranchId) -> Option<&ForkMine<VALIDATION_WINDOW_SIZE, B>> {
+        self.branches.get(&branch).map(|branch| &branch.mine)
+    }
+
+    pub fn status(&self, branch: BranchId) -> Option<BranchStatus> {
+        self.branches.get(&branch).map(Branch::status)
+    }
+
+    /// Status of every branch, by id.
+    pub fn statuses(&self) -> impl Iterator<Item = (BranchId, BranchStatus)> + '_ {
+        self.branches
+            .iter()
+            .map(|(id, branch)| (*id, branch.status()))
+    }
+
+    /// The valid branch with the most blocks. Ties go to the oldest branch.
+    pub fn longest_valid(&self) -> Option<BranchId> {
+        self.statuses()
+            .filter(|(_, status)| status.is_valid())
+            .max_by_key(|(id, status)| (status.total_blocks, std::cmp::Reverse(*id)))
+            .map(|(id, _)| id)
+    }
+
+    /// Remove `branch`, returning its mine. Branches forked from it are kept.
+    pub fn prune(&mut self, branch: BranchId) -> Option<ForkMine<VALIDATION_WINDOW_SIZE, B>> {
+        self.branches.remove(&branch).map(|branch| branch.mine)
+    }
+
+    /// Remove every invalid branch.
+    pub fn prune_invalid(&mut self) {
+        self.branches
+            .retain(|_, branch| branch.invalid_block.is_none());
+    }
+
+    /// Keep only the [longest valid](Self::longest_valid) branch, returning its id.
+    /// Nothing is removed if every branch is invalid.
+    pub fn select_longest_valid(&mut self) -> Option<BranchId> {
+        let longest = self.longest_valid()?;
+        self.branches.retain(|id, _| *id == longest);
+        Some(longest)
+    }
+
+    fn branch(
+        &self,
+        branch: BranchId,
+    ) -> Result<&Branch<VALIDATION_WINDOW_SIZE, B>, BranchError<VALIDATION_WINDOW_SIZE, B>> {
+        self.branches
+            .get(&branch)
+            .ok_or(BranchError::UnknownBranch(branch))
+    }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone>
+    Branch<VALIDATION_WINDOW_SIZE, B>
+{
+    fn status(&self) -> BranchStatus {
+        BranchStatus {
+            parent: self.parent,
+            total_blocks: self.mine.total_blocks,
+            invalid_block: self.invalid_block,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        test_data::{EXAMPLE, FIRST_INVALID_BLOCK_NUMBER},
+    };
+
+    #[test]
+    fn same_as_hash_mine() {
+        let mut fork_mine = ForkMine::<5, u64>::new(EXAMPLE[..5].try_into().unwrap());
+        let mut hash_mine = HashMine::<5, u64>::new(EXAMPLE[..5].try_into().unwrap());
+        for block in EXAMPLE[5..].iter().chain(&[4, 4, 2, 2, 8, 4]) {
+            assert_eq!(
+                fork_mine.try_extend_one(*block),
+                hash_mine.try_extend_one(*block)
+            );
+            assert_eq!(
+                fork_mine.validation_blocks().collect::<Vec<_>>(),
+                hash_mine.validation_blocks().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                fork_mine.distinct_pair_sums(),
+                hash_mine.distinct_pair_sums()
+            );
+        }
+    }
+
+    #[test]
+    fn forks_are_independent() {
+        let mine = ForkMine::<4, u64>::new([4, 4, 2, 2]);
+        let mut fork = mine.fork();
+        assert_eq!(fork.try_extend_one(8), Ok(()));
+        assert_eq!(
+            fork.validation_blocks().collect::<Vec<_>>(),
+            [&4, &2, &2, &8]
+        );
+        assert_eq!(
+            mine.validation_blocks().collect::<Vec<_>>(),
+            [&4, &4, &2, &2]
+        );
+        assert_eq!(mine.total_blocks(), 4);
+        assert_eq!(fork.total_blocks(), 5);
+    }
+
+    #[test]
+    fn branches() {
+        let mine = ForkMine::<5, u64>::new(EXAMPLE[..5].try_into().unwrap());
+        let mut branches = Branches::new(mine);
+        let root = BranchId::ROOT;
+        branches
+            .try_extend(root, EXAMPLE[5..10].iter().copied())
+            .unwrap();
+
+        // valid until 127
+        let main = branches.fork(root).unwrap();
+        assert_eq!(
+            branches.try_extend(main, EXAMPLE[10..].iter().copied()),
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER).into())
+        );
+        assert_eq!(
+            branches.try_extend_one(main, 219),
+            Err(BranchError::InvalidBranch {
+                branch: main,
+                block_number: 15
+            })
+        );
+
+        // conflicting continuation, valid
+        let alternative = branches.fork(root).unwrap();
+        branches.try_extend(alternative, [160, 157]).unwrap();
+
+        assert_eq!(
+            branches.statuses().collect::<Vec<_>>(),
+            [
+                (
+                    root,
+                    BranchStatus {
+                        parent: None,
+                        total_blocks: 10,
+                        invalid_block: None
+                    }
+                ),
+                (
+                    main,
+                    BranchStatus {
+                        parent: Some(root),
+                        total_blocks: 14,
+                        invalid_block: Some(15)
+                    }
+                ),
+                (
+                    alternative,
+                    BranchStatus {
+                        parent: Some(root),
+                        total_blocks: 12,
+                        invalid_block: None
+                    }
+                ),
+            ]
+        );
+        assert_eq!(branches.mine(root).unwrap().total_blocks(), 10);
+
+        assert_eq!(branches.longest_valid(), Some(alternative));
+        let tie = branches.fork(alternative).unwrap();
+        assert_eq!(branches.longest_valid(), Some(alternative));
+        assert_eq!(
+            branches.try_extend_one(tie, 1),
+            Err(MineError::InvalidBlock(1, 13).into())
+        );
+
+        branches.prune_invalid();
+        assert_eq!(
+            branches.statuses().map(|(id, _)| id).collect::<Vec<_>>(),
+            [root, alternative]
+        );
+        assert_eq!(branches.select_longest_valid(), Some(alternative));
+        assert!(branches.prune(root).is_none());
+        assert_eq!(branches.fork(main), Err(BranchError::UnknownBranch(main)));
+        assert_eq!(
+            branches.prune(alternative).map(|mine| mine.total_blocks()),
+            Some(12)
+        );
+        assert_eq!(branches.longest_valid(), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::mine::{Block, Mine, MineError};
+use crate::mine::{Block, Mine, MineError, RUNTIME_WINDOW_SIZE};
 
 use std::{collections::VecDeque, hash::Hash, ops::Add};
 
@@ -10,50 +10,72 @@ use multiset::HashMultiSet;
 ///
 /// [VALIDATION_WINDOW_SIZE]: Mine<VALIDATION_WINDOW_SIZE>
 #[derive(Clone, Debug)]
-pub struct HashMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Copy> {
+pub struct HashMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Hash + Clone> {
     /// Holds [VALIDATION_WINDOW_SIZE] blocks used for validation.
-    validation_blocks: VecDeque<B>,
+    pub(crate) validation_blocks: VecDeque<B>,
     /// Holds all the possible two element sums from the [validation_blocks](Self::validation_blocks).
     /// Used for quick validation of new blocks.
     block_pair_sums: HashMultiSet<B>,
     /// Used for tracking how many blocks have been validated
-    total_blocks: usize,
+    pub(crate) total_blocks: u64,
 }
 
-impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
-    for HashMine<VALIDATION_WINDOW_SIZE, B>
+impl<const VALIDATION_WINDOW_SIZE: usize, B> HashMine<VALIDATION_WINDOW_SIZE, B>
 where
-    B: Block + Hash + Copy,
+    B: Block + Hash + Clone,
     for<'a> &'a B: Add<&'a B, Output = B>,
-    for<'a> B: Add<&'a B, Output = B>,
 {
-    /// Create a new mine with given `initialization_blocks`.
-    /// No validation is performed on the initialization blocks.
-    /// # Performance
-    /// This is a potentially costly operation with the running time of O(VALIDATION_WINDOW_SIZE<sup>2</sup>).
-    fn new(initialization_blocks: [B; VALIDATION_WINDOW_SIZE]) -> Self {
+    /// Create a mine from a full window of `validation_blocks`, given in arrival order,
+    /// after `total_blocks` blocks have been added to the mine. Any number of blocks makes
+    /// a full window for a mine of [RUNTIME_WINDOW_SIZE].
+    /// No validation is performed on the window.
+    pub(crate) fn from_window(mut validation_blocks: VecDeque<B>, total_blocks: u64) -> Self {
+        let window_size = validation_blocks.len();
+        debug_assert!(
+            VALIDATION_WINDOW_SIZE == RUNTIME_WINDOW_SIZE || window_size == VALIDATION_WINDOW_SIZE
+        );
+        assert!(
+            total_blocks >= window_size as u64,
+            "A mine holds at least {window_size} blocks, not {total_blocks}."
+        );
+
         // Allocating half the max size. Worst case scenario with no overlapping sums
         // requires only 1 more allocation.
-        let capacity = VALIDATION_WINDOW_SIZE.pow(2) / 2;
+        let capacity = window_size.pow(2) / 2;
         let mut sums = HashMultiSet::with_capacity(capacity);
 
-        for (i, first) in initialization_blocks[0..VALIDATION_WINDOW_SIZE - 1]
-            .iter()
-            .enumerate()
-        {
-            for second in initialization_blocks.iter().skip(i + 1) {
+        let blocks = validation_blocks.make_contiguous();
+        for (i, first) in blocks.iter().enumerate() {
+            for second in blocks.iter().skip(i + 1) {
                 sums.insert(first + second);
             }
         }
 
         Self {
-            validation_blocks: VecDeque::from(initialization_blocks),
+            validation_blocks,
             block_pair_sums: sums,
-            total_blocks: VALIDATION_WINDOW_SIZE,
+            total_blocks,
         }
     }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
+    for HashMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Hash + Clone,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    type Error = MineError<VALIDATION_WINDOW_SIZE, B>;
+
+    /// Recreate a mine from its `validation_blocks`, see [Mine::resume].
+    /// No validation is performed on the validation blocks.
+    /// # Performance
+    /// This is a potentially costly operation with the running time of O(VALIDATION_WINDOW_SIZE<sup>2</sup>).
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        Self::from_window(VecDeque::from(validation_blocks), total_blocks)
+    }
 
-    fn try_extend_one(&mut self, new_block: B) -> Result<(), MineError<VALIDATION_WINDOW_SIZE, B>> {
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error> {
         if !self.block_pair_sums.contains(&new_block) {
             Err(MineError::InvalidBlock(new_block, self.total_blocks + 1))
         } else {
@@ -67,9 +89,9 @@ where
 
             for block in self.validation_blocks.iter() {
                 // remove all sums where the first block was a summand
-                self.block_pair_sums.remove(&(old_block + block));
+                self.block_pair_sums.remove(&(&old_block + block));
                 // add new sums where the new block is a summand
-                self.block_pair_sums.insert(new_block + block);
+                self.block_pair_sums.insert(&new_block + block);
             }
 
             self.validation_blocks.push_back(new_block);
@@ -78,21 +100,44 @@ where
             Ok(())
         }
     }
+
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error> {
+        if self.block_pair_sums.contains(new_block) {
+            Ok(())
+        } else {
+            Err(MineError::InvalidBlock(
+                new_block.clone(),
+                self.total_blocks + 1,
+            ))
+        }
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a,
+    {
+        self.validation_blocks.iter()
+    }
+
+    fn total_blocks(&self) -> u64 {
+        self.total_blocks
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        Some(self.block_pair_sums.distinct_elements().len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
-    use std::{
-        array,
-        fs::File,
-        io::{BufRead, BufReader},
-    };
+    use std::{array, fs::File, io::BufReader};
 
     use super::*;
 
     use crate::mine::Mine as MineTrait;
     use crate::mine::MineError;
+    use crate::parse::{Ingest, IngestError};
 
     // max size of values in the test file
     type Block = u128;
@@ -175,6 +220,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn accessors() {
+        let mut mine = Mine::new([4, 4, 2, 2]);
+        assert_eq!(mine.total_blocks(), 4);
+        assert_eq!(mine.ordered_validation_blocks(), None);
+        // 4 + 4, 4 + 2, 2 + 2
+        assert_eq!(mine.distinct_pair_sums(), Some(3));
+
+        assert_eq!(mine.try_extend_one(8), Ok(()));
+        assert_eq!(mine.total_blocks(), 5);
+        assert_eq!(
+            mine.validation_blocks().copied().collect::<Vec<_>>(),
+            [4, 2, 2, 8]
+        );
+        // 4 + 2, 4 + 8, 2 + 2, 2 + 8
+        assert_eq!(mine.distinct_pair_sums(), Some(4));
+    }
+
     #[test]
     fn smoke2() {
         let initial_blocks: [Block; 100] = array::from_fn(|i| i as Block + 1);
@@ -216,16 +279,36 @@ mod tests {
         let test_file = File::open(test_file_name).unwrap();
         let test_file = BufReader::new(test_file);
 
-        let blocks = test_file.lines().map(|block_value| {
-            block_value
-                .expect("test file must have only valid UTF-8 strings")
-                .trim()
-                .parse::<u128>()
-                .expect("test file must have only valid u128 values")
-        });
+        let result = Mine::<100, u128>::try_create_and_ingest(test_file);
 
-        let result = Mine::<100, u128>::try_create_and_extend(blocks);
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(315),
+                source: MineError::InvalidBlock(14, 315)
+            })
+        );
+    }
 
-        assert_eq!(result, Err(MineError::InvalidBlock(14, 315)));
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_bigint_file() {
+        use crate::mine::BigBlock;
+
+        let test_file_name = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/bigint_input.txt");
+        let test_file = File::open(test_file_name).unwrap();
+        let test_file = BufReader::new(test_file);
+
+        let result = Mine::<5, BigBlock>::try_create_and_ingest(test_file);
+
+        // the example scaled by 2^200
+        let scale = BigBlock::from(1u8) << 200;
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(15),
+                source: MineError::InvalidBlock(127u8 * scale, 15)
+            })
+        );
     }
 }
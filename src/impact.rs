@@ -0,0 +1,156 @@
+use crate::{
+    mine::{Block, Mine},
+    report::graph::JustificationGraph,
+};
+
+use std::ops::RangeInclusive;
+
+/// Effect of correcting the value of one block in a validated stream, see [correct].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Impact {
+    /// Blocks validated again after the corrected one. Initialization blocks in the range
+    /// aren't validated.
+    pub region: RangeInclusive<u64>,
+    /// Blocks rejected when validated again, in ascending order. Holds the corrected block
+    /// itself if it isn't an initialization block and is no longer valid.
+    pub invalid_blocks: Vec<u64>,
+}
+
+impl Impact {
+    pub fn is_harmless(&self) -> bool {
+        self.invalid_blocks.is_empty()
+    }
+}
+
+/// Validate again the blocks recorded in `graph` from block number `block_number` on, its
+/// value replaced by `corrected`. Usually for initialization blocks, which
+/// [Mine::new](crate::mine::Mine::new) doesn't validate.
+///
+/// The blocks are replayed through an `M` [resumed](Mine::resume) with the window of the
+/// corrected block, so a block rejected on the way is left out of the windows of the
+/// blocks after it, as [Mine::try_extend_one] would. Validation stops once the corrected
+/// block left the window and the window is the recorded one again, or at the end of the
+/// graph if a block was rejected.
+///
+/// Blocks that can become invalid are a subset of the
+/// [dependents](JustificationGraph::dependents) of the corrected block, and of the blocks
+/// after a rejected one. They can still be valid through another pair.
+///
+/// `None` if the block or the window before it isn't in the graph.
+pub fn correct<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+    graph: &JustificationGraph<B>,
+    block_number: u64,
+    corrected: B,
+) -> Option<Impact>
+where
+    B: Block + Clone,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+    graph.value(block_number)?;
+    let first_block_number = graph.block_numbers().next()?;
+    let last_block_number = graph.block_numbers().last()?;
+    let window_size = VALIDATION_WINDOW_SIZE as u64;
+
+    let value = |number: u64| {
+        if number == block_number {
+            Some(&corrected)
+        } else {
+            graph.value(number)
+        }
+    };
+
+    // the first block validated again, the corrected one unless it's an initialization block
+    let first_validated = block_number.max(first_block_number + window_size);
+    let window: Vec<B> = (first_validated - window_size..first_validated)
+        .map(|number| value(number).cloned())
+        .collect::<Option<_>>()?;
+    let mut mine = M::resume(window.try_into().ok()?, first_validated - 1);
+
+    let mut region_end = block_number;
+    let mut invalid_blocks = Vec::new();
+    for number in first_validated..=last_block_number {
+        let block = value(number)?.clone();
+        if mine.try_extend_one(block).is_err() {
+            invalid_blocks.push(number);
+        }
+        region_end = number;
+
+        // from here on the blocks are validated as recorded
+        let recorded_window = (number + 1 - window_size..=number).map(|number| graph.value(number));
+        if number >= block_number + window_size
+            && mine.validation_blocks().map(Some).eq(recorded_window)
+        {
+            break;
+        }
+    }
+
+    Some(Impact {
+        region: block_number + 1..=region_end,
+        invalid_blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{hash_mine::HashMine, test_data::RECOVERING_EXAMPLE, two_ptr_mine::TwoPtrMine};
+
+    fn graph() -> JustificationGraph<u64> {
+        let mut mine = HashMine::<5, u64>::new(RECOVERING_EXAMPLE[..5].try_into().unwrap());
+        let mut graph = JustificationGraph::new(&mine);
+        for block in &RECOVERING_EXAMPLE[5..] {
+            let _ = graph.try_extend_one(&mut mine, *block);
+        }
+        graph
+    }
+
+    #[test]
+    fn initialization_blocks() {
+        let graph = graph();
+
+        // never justifies anything
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 1, 36).unwrap();
+        assert_eq!(impact.region, 2..=6);
+        assert!(impact.is_harmless());
+
+        // 40 = 15 + 25 and 62 = 15 + 47 no longer hold, 55 = 35 + 20 still does, but
+        // without 40 and 62 in the window most blocks after them are rejected too
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 3, 16).unwrap();
+        assert_eq!(impact.region, 4..=17);
+        assert_eq!(impact.invalid_blocks, [6, 7, 9, 10, 12, 13, 14, 15, 16, 17]);
+        // the same value is harmless, the window is the recorded one again at block 8
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 3, 15).unwrap();
+        assert_eq!(impact.region, 4..=8);
+        assert!(impact.is_harmless());
+        assert_eq!(correct::<5, _, TwoPtrMine<5, u64>>(&graph, 18, 0), None);
+    }
+
+    #[test]
+    fn validated_blocks() {
+        let graph = graph();
+
+        // 219 = 102 + 117 and 401 = 182 + 219 no longer hold
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 15, 218).unwrap();
+        assert_eq!(impact.region, 16..=17);
+        assert_eq!(impact.invalid_blocks, [15, 17]);
+
+        // 124 is rejected itself, so it can't justify 219 = 95 + 124 in place of 102 + 117
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 12, 124).unwrap();
+        assert_eq!(impact.region, 13..=17);
+        assert_eq!(impact.invalid_blocks, [12, 14, 15, 16, 17]);
+
+        let impact = correct::<5, _, TwoPtrMine<5, u64>>(&graph, 17, 1).unwrap();
+        assert!(impact.region.is_empty());
+        assert_eq!(impact.invalid_blocks, [17]);
+
+        let mine = HashMine::<5, u64>::new(RECOVERING_EXAMPLE[..5].try_into().unwrap());
+        let graph = JustificationGraph::new(&mine);
+        assert!(correct::<5, _, TwoPtrMine<5, u64>>(&graph, 5, 1)
+            .unwrap()
+            .region
+            .is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::mine::Block;
+
+use std::{
+    cmp::Ordering,
+    ops::{Add, RangeInclusive},
+};
+
+/// How a stream fares with one validation window size, see [infer_window_sizes].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowFit {
+    /// Every block after the initialization blocks is valid.
+    Valid,
+    /// Number of the first invalid block.
+    InvalidBlock(u64),
+    /// The stream doesn't fill the validation window.
+    TooShort,
+}
+
+/// Result of [infer_window_sizes].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowSizes {
+    /// Number of blocks in the stream. Less than its length if every size failed early.
+    pub total_blocks: u64,
+    /// Outcome of every window size in the range, in ascending order of size.
+    pub fits: Vec<(usize, WindowFit)>,
+}
+
+impl WindowSizes {
+    /// Window sizes the stream is fully valid with.
+    pub fn valid_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.fits
+            .iter()
+            .filter(|(_, fit)| *fit == WindowFit::Valid)
+            .map(|(size, _)| *size)
+    }
+
+    pub fn fit(&self, window_size: usize) -> Option<WindowFit> {
+        self.fits
+            .iter()
+            .find(|(size, _)| *size == window_size)
+            .map(|(_, fit)| *fit)
+    }
+}
+
+/// Validate the `blocks` with every validation window size in `window_sizes` in a single
+/// pass, for streams of unknown window size.
+///
+/// A block is valid with a window size iff its two closest summands, the ones whose older
+/// block is the newest, fit in the window. That distance is found once per block, with a
+/// two pointer search over the largest window kept in ascending order, and compared against
+/// every size. So the pass costs about as much as validating with a
+/// [TwoPtrMine](crate::two_ptr_mine::TwoPtrMine) of the largest size, plus a comparison per
+/// size. Only the largest window is kept in memory.
+///
+/// Up to its first invalid block a stream is validated the same by a [Mine](crate::mine::Mine),
+/// so [WindowFit::InvalidBlock] is where [Mine::try_extend](crate::mine::Mine::try_extend)
+/// would fail. Reading stops once every size failed.
+pub fn infer_window_sizes<B>(
+    blocks: impl IntoIterator<Item = B>,
+    window_sizes: RangeInclusive<usize>,
+) -> WindowSizes
+where
+    B: Block + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let max_size = *window_sizes.end();
+    let mut fits: Vec<_> = window_sizes.map(|size| (size, None)).collect();
+    // blocks of the largest window with their block numbers, in ascending order
+    let mut window: Vec<(B, u64)> = Vec::with_capacity(max_size);
+    let mut total_blocks = 0;
+
+    for block in blocks {
+        if fits.iter().all(|(_, fit)| fit.is_some()) {
+            break;
+        }
+        total_blocks += 1;
+
+        let distance = closest_summands(&window, &block)
+            .map(|older_block_number| (total_blocks - older_block_number) as usize);
+        for (size, fit) in &mut fits {
+            let validated = total_blocks > *size as u64;
+            if fit.is_none() && validated && distance.is_none_or(|distance| distance > *size) {
+                *fit = Some(WindowFit::InvalidBlock(total_blocks));
+            }
+        }
+
+        if window.len() == max_size {
+            let oldest = total_blocks - max_size as u64;
+            window.retain(|(_, block_number)| *block_number != oldest);
+        }
+        if max_size > 0 {
+            let entry = (block, total_blocks);
+            let index = window.binary_search(&entry).unwrap_or_else(|i| i);
+            window.insert(index, entry);
+        }
+    }
+
+    let fits = fits
+        .into_iter()
+        .map(|(size, fit)| {
+            let fit = fit.unwrap_or(if total_blocks < size as u64 {
+                WindowFit::TooShort
+            } else {
+                WindowFit::Valid
+            });
+            (size, fit)
+        })
+        .collect();
+
+    WindowSizes { total_blocks, fits }
+}
+
+/// Number of the older of the two blocks in the `window` that sum up to `block`, for the
+/// pair whose older block is the newest. `None` if there are none.
+///
+/// The `window` holds blocks with their block numbers, in ascending order. Equal blocks are
+/// ordered by block number, so the newest of a run of equal blocks is its last one.
+fn closest_summands<B>(window: &[(B, u64)], block: &B) -> Option<u64>
+where
+    B: Block + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let mut closest = None;
+    let (mut low, mut high) = (0, window.len().checked_sub(1)?);
+
+    while low < high {
+        let (small, large) = (&window[low].0, &window[high].0);
+        match (small + large).cmp(block) {
+            Ordering::Less => low += 1,
+            Ordering::Greater => high -= 1,
+            // every block between the two is equal, the newest two are the closest pair
+            Ordering::Equal if small == large => {
+                return closest.max(Some(window[high - 1].1));
+            }
+            Ordering::Equal => {
+                // `high` is the newest of its run of equal blocks, `low` the oldest
+                while window[low + 1].0 == *small {
+                    low += 1;
+                }
+                closest = closest.max(Some(window[low].1.min(window[high].1)));
+
+                low += 1;
+                while window[high - 1].0 == *large {
+                    high -= 1;
+                }
+                high -= 1;
+            }
+        }
+    }
+
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::{Mine, MineError},
+        parse::BlockReader,
+        test_data::RECOVERING_EXAMPLE,
+    };
+
+    #[test]
+    fn example() {
+        let sizes = infer_window_sizes(RECOVERING_EXAMPLE[..14].iter().copied(), 0..=16);
+        assert_eq!(sizes.total_blocks, 14);
+        // a larger window holds the summands of a smaller one
+        assert_eq!(
+            sizes.valid_sizes().collect::<Vec<_>>(),
+            (5..=14).collect::<Vec<_>>()
+        );
+        assert_eq!(sizes.fit(4), Some(WindowFit::InvalidBlock(5)));
+        assert_eq!(sizes.fit(5), Some(WindowFit::Valid));
+        assert_eq!(sizes.fit(17), None);
+        assert_eq!(sizes.fit(15), Some(WindowFit::TooShort));
+        assert_eq!(sizes.fit(0), Some(WindowFit::InvalidBlock(1)));
+        assert_eq!(sizes.fit(1), Some(WindowFit::InvalidBlock(2)));
+
+        // same as a mine of each size
+        fn first_invalid<const VALIDATION_WINDOW_SIZE: usize>() -> u64 {
+            match HashMine::<VALIDATION_WINDOW_SIZE, u64>::try_create_and_extend(RECOVERING_EXAMPLE)
+            {
+                Err(MineError::InvalidBlock(_, block_number)) => block_number,
+                result => panic!("unexpected result {result:?}"),
+            }
+        }
+        let sizes = infer_window_sizes(RECOVERING_EXAMPLE.iter().copied(), 2..=6);
+        assert_eq!(
+            sizes.fits,
+            [
+                (2, WindowFit::InvalidBlock(first_invalid::<2>())),
+                (3, WindowFit::InvalidBlock(first_invalid::<3>())),
+                (4, WindowFit::InvalidBlock(first_invalid::<4>())),
+                (5, WindowFit::InvalidBlock(first_invalid::<5>())),
+                (6, WindowFit::InvalidBlock(first_invalid::<6>())),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_once_every_size_failed() {
+        let blocks = [1u64, 2, 100].into_iter().chain(3..);
+        let sizes = infer_window_sizes(blocks, 2..=2);
+        assert_eq!(sizes.total_blocks, 3);
+        assert_eq!(sizes.fits, [(2, WindowFit::InvalidBlock(3))]);
+    }
+
+    #[test]
+    fn repeated_blocks() {
+        // blocks are sorted by value, the closest pair is found by block number
+        let window = [(1u64, 4), (1, 6), (2, 2), (2, 5), (3, 1), (3, 7)];
+        assert_eq!(closest_summands(&window, &2), Some(4));
+        assert_eq!(closest_summands(&window, &4), Some(6));
+        assert_eq!(closest_summands(&window, &5), Some(5));
+        assert_eq!(closest_summands(&window, &6), Some(1));
+        assert_eq!(closest_summands(&window, &7), None);
+        assert_eq!(closest_summands(&window[..1], &2), None);
+        assert_eq!(closest_summands::<u64>(&[], &2), None);
+
+        let sizes = infer_window_sizes([3u64, 1, 1, 2, 3, 4, 3], 2..=4);
+        assert_eq!(
+            sizes.fits,
+            [
+                (2, WindowFit::InvalidBlock(3)),
+                (3, WindowFit::InvalidBlock(7)),
+                (4, WindowFit::Valid),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file() {
+        let test_file_name = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/challenge_input.txt");
+        let reader = BufReader::new(File::open(test_file_name).unwrap());
+        let blocks = BlockReader::<_, u128>::new(reader).map(Result::unwrap);
+
+        let sizes = infer_window_sizes(blocks, 95..=105);
+        assert_eq!(sizes.fit(100), Some(WindowFit::InvalidBlock(315)));
+    }
+}
@@ -0,0 +1,580 @@
+use crate::mine::{try_extend_recorded, Block, Mine};
+
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+/// Digest chaining the entries of a ledger, see [LedgerWriter].
+pub trait ChainDigest {
+    type Hash: AsRef<[u8]>;
+
+    /// Hash of the entry holding `value`, the `block_number`-th block, following the
+    /// entry hashed to `previous`. `previous` is empty for the first entry.
+    fn chain(&self, previous: &[u8], block_number: u64, value: &str) -> Self::Hash;
+}
+
+/// 64 bit [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function)
+/// digest. Fast and dependency free, but it only detects accidental edits.
+/// Use [Sha256] against deliberate tampering.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fnv1a;
+
+impl ChainDigest for Fnv1a {
+    type Hash = [u8; 8];
+
+    fn chain(&self, previous: &[u8], block_number: u64, value: &str) -> Self::Hash {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let block_number = block_number.to_le_bytes();
+        let bytes = previous.iter().chain(&block_number).chain(value.as_bytes());
+
+        bytes
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+            })
+            .to_be_bytes()
+    }
+}
+
+/// SHA-256 digest. Requires the `sha2` feature.
+#[cfg(feature = "sha2")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256;
+
+#[cfg(feature = "sha2")]
+impl ChainDigest for Sha256 {
+    type Hash = [u8; 32];
+
+    fn chain(&self, previous: &[u8], block_number: u64, value: &str) -> Self::Hash {
+        use sha2::Digest;
+
+        sha2::Sha256::new()
+            .chain_update(previous)
+            .chain_update(block_number.to_le_bytes())
+            .chain_update(value)
+            .finalize()
+            .into()
+    }
+}
+
+/// Writes a tamper-evident ledger of accepted blocks.
+///
+/// Every block is an entry on its own line, holding the block number, the block value and
+/// the hex encoded hash of the two chained to the hash of the previous entry:
+/// ```text
+/// 1 35 5101c3c89d8156e0
+/// 2 20 4410285460a96110
+/// ```
+/// Editing, removing or reordering entries breaks the chain, see [verify]. Cutting entries
+/// off the end, or rewriting the whole ledger, doesn't: keep the [LedgerWriter::head] apart
+/// from the ledger to detect those.
+#[derive(Debug)]
+pub struct LedgerWriter<W, D: ChainDigest> {
+    writer: W,
+    digest: D,
+    /// Hash of the last entry.
+    head: Option<D::Hash>,
+}
+
+impl<W: Write, D: ChainDigest> LedgerWriter<W, D> {
+    pub fn new(writer: W, digest: D) -> Self {
+        Self {
+            writer,
+            digest,
+            head: None,
+        }
+    }
+
+    /// Commit the validation window of a new `mine`, its initialization blocks.
+    pub fn commit_window<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &M,
+    ) -> io::Result<()>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let first_block_number = mine.total_blocks() + 1 - VALIDATION_WINDOW_SIZE as u64;
+        for (block_number, block) in (first_block_number..).zip(mine.validation_blocks()) {
+            self.commit(block_number, block)?;
+        }
+        Ok(())
+    }
+
+    /// Validate `new_block` and commit it if it's accepted, see [try_extend_recorded].
+    pub fn try_extend_one<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &mut M,
+        new_block: B,
+    ) -> io::Result<Result<(), M::Error>>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        try_extend_recorded(
+            mine,
+            new_block,
+            |mine, block, validation| match validation {
+                Ok(()) => self.commit(mine.total_blocks() + 1, block),
+                Err(_) => Ok(()),
+            },
+        )
+    }
+
+    /// Append the entry of an accepted block.
+    pub fn commit(&mut self, block_number: u64, value: &impl Display) -> io::Result<()> {
+        let value = value.to_string();
+        let previous = self.head.as_ref().map_or(&[][..], AsRef::as_ref);
+        let hash = self.digest.chain(previous, block_number, &value);
+
+        writeln!(
+            self.writer,
+            "{block_number} {value} {}",
+            to_hex(hash.as_ref())
+        )?;
+        self.head = Some(hash);
+        Ok(())
+    }
+
+    /// Hash of the last entry, commits to the whole ledger.
+    pub fn head(&self) -> Option<&D::Hash> {
+        self.head.as_ref()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Problem found by [verify] on a line of the ledger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Line of the entry, starting from 1.
+    pub line: usize,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The hash of the entry doesn't match its content and the previous entry.
+    /// The entry was edited, or the one before it was edited or removed.
+    ChainBreak,
+    /// The mine rejects the block, with the error message.
+    InvalidBlock(String),
+    /// The block number doesn't follow the previous entry.
+    UnexpectedBlockNumber { expected: u64, actual: u64 },
+    /// The entry can't be read.
+    Malformed(String),
+    /// The mine can't be created from the initialization blocks, with the error message.
+    InvalidInitialization(String),
+    /// The ledger ends before the validation window is full.
+    TooShort,
+    /// The hash of the last entry isn't the expected head. Entries were cut off the end,
+    /// or the whole ledger was rewritten.
+    HeadMismatch,
+}
+
+/// Result of [verify].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Verification {
+    /// Number of entries read.
+    pub entries: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl Verification {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Replay a ledger written by [LedgerWriter] through a new `M`, checking both the hash
+/// chain and the blocks.
+///
+/// Verification doesn't stop at the first violation. The chain is followed through the
+/// stored hashes, so an edited entry is reported on its own. Rejected blocks are skipped.
+///
+/// The chain alone can't tell a truncated ledger, or one rewritten with recomputed hashes,
+/// from the original. Pass the `expected_head`, the [LedgerWriter::head] kept apart from the
+/// ledger, to anchor it. Anchoring only detects rewrites with a digest that can't be forged,
+/// like [Sha256], not with [Fnv1a].
+pub fn verify<const VALIDATION_WINDOW_SIZE: usize, B, M, D>(
+    reader: impl BufRead,
+    digest: &D,
+    expected_head: Option<&D::Hash>,
+) -> io::Result<Verification>
+where
+    B: Block + FromStr,
+    B::Err: Display,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    M::Error: Display,
+    D: ChainDigest,
+{
+    let mut verification = Verification::default();
+    let mut previous_hash = Vec::new();
+    let mut next_block_number = Some(1);
+    let mut initialization_blocks = Vec::with_capacity(VALIDATION_WINDOW_SIZE);
+    let mut mine: Option<M> = None;
+    let mut initialization_failed = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_number = i + 1;
+        let mut violation = |kind| {
+            verification.violations.push(Violation {
+                line: line_number,
+                kind,
+            })
+        };
+
+        let line = line?;
+        let (block_number, value, hash) = match parse_entry(&line) {
+            Ok(entry) => entry,
+            Err(reason) => {
+                violation(ViolationKind::Malformed(reason));
+                continue;
+            }
+        };
+
+        match next_block_number {
+            Some(expected) if block_number != expected => {
+                violation(ViolationKind::UnexpectedBlockNumber {
+                    expected,
+                    actual: block_number,
+                });
+            }
+            Some(_) => {}
+            None => violation(ViolationKind::Malformed(format!(
+                "no block can follow block {}",
+                u64::MAX
+            ))),
+        }
+        next_block_number = block_number.checked_add(1);
+
+        if digest.chain(&previous_hash, block_number, value).as_ref() != hash {
+            violation(ViolationKind::ChainBreak);
+        }
+        previous_hash = hash;
+
+        match value.parse::<B>() {
+            Ok(block) => match &mut mine {
+                Some(mine) => {
+                    if let Err(error) = mine.try_extend_one(block) {
+                        violation(ViolationKind::InvalidBlock(error.to_string()));
+                    }
+                }
+                None if initialization_failed => {}
+                None => {
+                    initialization_blocks.push(block);
+                    if initialization_blocks.len() == VALIDATION_WINDOW_SIZE {
+                        match M::try_new(std::mem::take(&mut initialization_blocks)) {
+                            Ok(new_mine) => mine = Some(new_mine),
+                            Err(error) => {
+                                violation(ViolationKind::InvalidInitialization(error.to_string()));
+                                initialization_failed = true;
+                            }
+                        }
+                    }
+                }
+            },
+            Err(error) => violation(ViolationKind::Malformed(error.to_string())),
+        }
+
+        verification.entries += 1;
+    }
+
+    let end = verification.entries as usize + 1;
+    if mine.is_none() && !initialization_failed {
+        verification.violations.push(Violation {
+            line: end,
+            kind: ViolationKind::TooShort,
+        });
+    }
+    if expected_head.is_some_and(|head| head.as_ref() != previous_hash) {
+        verification.violations.push(Violation {
+            line: end,
+            kind: ViolationKind::HeadMismatch,
+        });
+    }
+
+    Ok(verification)
+}
+
+/// Block number, value and hash of a ledger entry.
+fn parse_entry(line: &str) -> Result<(u64, &str, Vec<u8>), String> {
+    let mut fields = line.split_whitespace();
+    let (Some(block_number), Some(value), Some(hash), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err("expected a block number, a value and a hash".to_owned());
+    };
+
+    let block_number = block_number
+        .parse()
+        .map_err(|_| format!("invalid block number {block_number:?}"))?;
+    let hash = from_hex(hash).ok_or_else(|| format!("invalid hash {hash:?}"))?;
+
+    Ok((block_number, value, hash))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for byte in bytes {
+        write!(hex, "{byte:02x}").expect("writing to a String can't fail");
+    }
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine, mine::MineError, test_data::EXAMPLE, two_ptr_mine::TwoPtrMine,
+    };
+
+    fn write_example<D: ChainDigest>(digest: D) -> Vec<String>
+    where
+        D::Hash: Clone,
+    {
+        write_anchored_example(digest).0
+    }
+
+    fn write_anchored_example<D: ChainDigest>(digest: D) -> (Vec<String>, D::Hash)
+    where
+        D::Hash: Clone,
+    {
+        let mut mine = HashMine::<5, u64>::try_new(&EXAMPLE[..5]).unwrap();
+        let mut writer = LedgerWriter::new(Vec::new(), digest);
+        writer.commit_window(&mine).unwrap();
+        for block in &EXAMPLE[5..15] {
+            let _ = writer.try_extend_one(&mut mine, *block).unwrap();
+        }
+
+        let head = writer.head().unwrap().clone();
+        let ledger = String::from_utf8(writer.finish().unwrap()).unwrap();
+        (ledger.lines().map(str::to_owned).collect(), head)
+    }
+
+    fn verify_lines(lines: &[String]) -> Verification {
+        verify::<5, u64, TwoPtrMine<5, u64>, _>(lines.join("\n").as_bytes(), &Fnv1a, None).unwrap()
+    }
+
+    fn violation(line: usize, kind: ViolationKind) -> Violation {
+        Violation { line, kind }
+    }
+
+    #[test]
+    fn fnv1a() {
+        assert_eq!(to_hex(&Fnv1a.chain(&[], 0, "")[..]), "a8c7f832281a39c5");
+        assert_eq!(from_hex("00ff7f"), Some(vec![0, 0xff, 0x7f]));
+        assert_eq!(from_hex("0"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[test]
+    fn valid_ledger() {
+        let lines = write_example(Fnv1a);
+        assert_eq!(lines.len(), 14, "The rejected block isn't committed");
+        assert!(lines[5].starts_with("6 40 "));
+
+        let verification = verify_lines(&lines);
+        assert_eq!(verification.entries, 14);
+        assert!(verification.is_valid());
+    }
+
+    #[test]
+    fn tampering() {
+        let lines = write_example(Fnv1a);
+
+        // a valid sum, but not the committed block
+        let mut edited = lines.clone();
+        edited[13] = edited[13].replace("14 182 ", "14 197 ");
+        assert_eq!(
+            verify_lines(&edited).violations,
+            [violation(14, ViolationKind::ChainBreak)]
+        );
+
+        edited[13] = edited[13].replace("14 197 ", "14 183 ");
+        assert_eq!(
+            verify_lines(&edited).violations,
+            [
+                violation(14, ViolationKind::ChainBreak),
+                violation(
+                    14,
+                    ViolationKind::InvalidBlock(
+                        MineError::<5, u64>::InvalidBlock(183, 14).to_string()
+                    )
+                ),
+            ]
+        );
+
+        // rehashing the edited entry moves the break to the next one
+        let mut rehashed = lines.clone();
+        let previous = from_hex(lines[11].rsplit(' ').next().unwrap()).unwrap();
+        let hash = Fnv1a.chain(&previous, 13, "157");
+        rehashed[12] = format!("13 157 {}", to_hex(&hash));
+        assert_eq!(
+            verify_lines(&rehashed).violations,
+            [violation(14, ViolationKind::ChainBreak)]
+        );
+
+        let mut removed = lines.clone();
+        removed.remove(8);
+        assert_eq!(
+            verify_lines(&removed).violations[..2],
+            [
+                violation(
+                    9,
+                    ViolationKind::UnexpectedBlockNumber {
+                        expected: 9,
+                        actual: 10
+                    }
+                ),
+                violation(9, ViolationKind::ChainBreak),
+            ]
+        );
+
+        let mut malformed = lines.clone();
+        malformed[2] = "3 15".to_owned();
+        malformed[3] = "4 x 00".to_owned();
+        assert_eq!(
+            verify_lines(&malformed).violations[..5],
+            [
+                violation(
+                    3,
+                    ViolationKind::Malformed(
+                        "expected a block number, a value and a hash".to_owned()
+                    )
+                ),
+                violation(
+                    4,
+                    ViolationKind::UnexpectedBlockNumber {
+                        expected: 3,
+                        actual: 4
+                    }
+                ),
+                violation(4, ViolationKind::ChainBreak),
+                violation(
+                    4,
+                    ViolationKind::Malformed("invalid digit found in string".to_owned())
+                ),
+                violation(5, ViolationKind::ChainBreak),
+            ]
+        );
+
+        assert_eq!(
+            verify_lines(&lines[..4]).violations,
+            [violation(5, ViolationKind::TooShort)]
+        );
+
+        let mut overflowing = lines.clone();
+        overflowing[5] = format!("{} 40 00", u64::MAX);
+        overflowing[6] = "0 62 00".to_owned();
+        assert_eq!(
+            verify_lines(&overflowing).violations[..4],
+            [
+                violation(
+                    6,
+                    ViolationKind::UnexpectedBlockNumber {
+                        expected: 6,
+                        actual: u64::MAX
+                    }
+                ),
+                violation(6, ViolationKind::ChainBreak),
+                violation(
+                    7,
+                    ViolationKind::Malformed(format!("no block can follow block {}", u64::MAX))
+                ),
+                violation(7, ViolationKind::ChainBreak),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_write() {
+        let mut mine = HashMine::<5, u64>::try_new(&EXAMPLE[..5]).unwrap();
+        let mut writer = LedgerWriter::new(&mut [0; 0][..], Fnv1a);
+        assert!(writer.try_extend_one(&mut mine, EXAMPLE[5]).is_err());
+        assert_eq!(
+            mine.total_blocks(),
+            5,
+            "Uncommitted blocks stay out of the mine"
+        );
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha256() {
+        let lines = write_example(Sha256);
+        assert_eq!(lines[0].len(), "1 35 ".len() + 64);
+
+        let ledger = lines.join("\n");
+        let verification =
+            verify::<5, u64, HashMine<5, u64>, _>(ledger.as_bytes(), &Sha256, None).unwrap();
+        assert!(verification.is_valid());
+
+        let verification =
+            verify::<5, u64, HashMine<5, u64>, _>(ledger.as_bytes(), &Fnv1a, None).unwrap();
+        assert_eq!(verification.violations.len(), 14);
+    }
+
+    #[test]
+    fn anchored() {
+        let (lines, head) = write_anchored_example(Fnv1a);
+        let verify_anchored = |lines: &[String]| {
+            verify::<5, u64, TwoPtrMine<5, u64>, _>(
+                lines.join("\n").as_bytes(),
+                &Fnv1a,
+                Some(&head),
+            )
+            .unwrap()
+        };
+        assert!(verify_anchored(&lines).is_valid());
+
+        // a truncated ledger is still a valid chain
+        let truncated = &lines[..10];
+        assert!(verify_lines(truncated).is_valid());
+        assert_eq!(
+            verify_anchored(truncated).violations,
+            [violation(11, ViolationKind::HeadMismatch)]
+        );
+
+        // and so is a ledger rewritten from the start
+        let mut rewritten = LedgerWriter::new(Vec::new(), Fnv1a);
+        for (block_number, block) in (1..).zip(&EXAMPLE[..10]) {
+            rewritten.commit(block_number, &(block * 2)).unwrap();
+        }
+        let rewritten = String::from_utf8(rewritten.finish().unwrap()).unwrap();
+        let rewritten: Vec<String> = rewritten.lines().map(str::to_owned).collect();
+        assert!(verify_lines(&rewritten).is_valid());
+        assert_eq!(
+            verify_anchored(&rewritten).violations,
+            [violation(11, ViolationKind::HeadMismatch)]
+        );
+
+        assert_eq!(
+            verify_anchored(&[]).violations,
+            [
+                violation(1, ViolationKind::TooShort),
+                violation(1, ViolationKind::HeadMismatch)
+            ]
+        );
+    }
+}
@@ -1,3 +1,29 @@
+pub mod adaptive_mine;
+pub mod archive;
+pub mod binary;
+pub mod certificate;
+pub mod checkpoint;
+pub mod compress;
+#[cfg(feature = "csv")]
+pub mod csv_input;
+pub mod dyn_mine;
+#[cfg(feature = "fork")]
+pub mod fork_mine;
 pub mod hash_mine;
+pub mod impact;
+pub mod infer;
+pub mod ledger;
 pub mod mine;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod number;
+pub mod parse;
+pub mod policy;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(test)]
+mod test_data;
 pub mod two_ptr_mine;
+pub mod wal;
+pub mod widening_mine;
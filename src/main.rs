@@ -0,0 +1,544 @@
+//! `tmmt` command line validator. Run `tmmt --help` for usage.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    hash::Hash,
+    io::{self, BufRead, Write},
+    ops::Add,
+    process::ExitCode,
+    str::FromStr,
+};
+
+use thiserror::Error;
+use tmmt::{
+    adaptive_mine::{AdaptiveMine, Strategy},
+    compress::DecompressedReader,
+    mine::{Block, Mine, MineError, RUNTIME_WINDOW_SIZE},
+    number::{Integer, NumberFormat, NumberParser},
+    parse::{BlockReader, ParseError},
+    policy::{PolicyMine, SummandPolicy},
+    report::jsonl::{JsonLinesWriter, Verbosity},
+};
+
+const USAGE: &str = "\
+Validate a stream of blocks, one block per line.
+
+Usage: tmmt [OPTIONS] [FILE]
+
+Reads FILE, or the standard input if FILE is `-` or missing.
+gzip and zstd compressed input is detected and decompressed, if the features are enabled.
+
+Options:
+  -w, --window <SIZE>        Validation window size, at least 1 [default: 100]
+  -b, --block <TYPE>         Block type: u64, u128, i128 or bigint [default: u128]
+  -i, --impl <IMPL>          Implementation: hash, two-ptr or auto [default: auto]
+  -e, --expected <BLOCKS>    Expected number of blocks, helps `--impl auto` pick an implementation
+  -n, --numbers <SYNTAX>     Number syntax: decimal, or any for hex, scientific and `_` separated
+                             numbers too [default: decimal]
+  -s, --summands <POLICY>    Summand policy: distinct-positions or distinct-values
+                             [default: distinct-positions]
+  -r, --report               Report every invalid block instead of stopping at the first one.
+                             Invalid blocks are skipped and reported by line.
+  -f, --format <FORMAT>      Output format: text or jsonl [default: text]
+                             jsonl writes a JSON object for every block followed by a summary.
+  -h, --help                 Print help
+
+Exit status:
+  0  all blocks are valid
+  1  an invalid block was found
+  2  input error
+";
+
+/// Exit status when all the blocks are valid.
+const EXIT_VALID: u8 = 0;
+/// Exit status when an invalid block was found.
+const EXIT_INVALID: u8 = 1;
+/// Exit status when the input or the arguments can't be used.
+const EXIT_INPUT_ERROR: u8 = 2;
+
+/// Mine of the validator, the window size is given on the command line.
+type CliMine<B> = PolicyMine<AdaptiveMine<RUNTIME_WINDOW_SIZE, B>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BlockType {
+    U64,
+    U128,
+    I128,
+    #[cfg(feature = "bigint")]
+    BigInt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Implementation {
+    Hash,
+    TwoPtr,
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Text,
+    JsonLines,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Options {
+    window_size: usize,
+    block_type: BlockType,
+    implementation: Implementation,
+    expected_blocks: Option<usize>,
+    numbers: NumberFormat,
+    policy: SummandPolicy,
+    report: bool,
+    format: Format,
+    /// `None` reads the standard input.
+    input: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            window_size: 100,
+            block_type: BlockType::U128,
+            implementation: Implementation::Auto,
+            expected_blocks: None,
+            numbers: NumberFormat::default(),
+            policy: SummandPolicy::DistinctPositions,
+            report: false,
+            format: Format::Text,
+            input: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Help,
+    Validate(Options),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Valid,
+    Invalid,
+}
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("{0}\nRun `tmmt --help` for usage.")]
+    Usage(String),
+    #[error("Failed to read input: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("Input has {0} blocks, not enough to fill the validation window.")]
+    TooShort(usize),
+}
+
+fn main() -> ExitCode {
+    let exit_status = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            EXIT_VALID
+        }
+        Ok(Command::Validate(options)) => match open_and_run(&options) {
+            Ok(Outcome::Valid) => EXIT_VALID,
+            Ok(Outcome::Invalid) => EXIT_INVALID,
+            Err(error) => {
+                eprintln!("tmmt: {error}");
+                EXIT_INPUT_ERROR
+            }
+        },
+        Err(error) => {
+            eprintln!("tmmt: {error}");
+            EXIT_INPUT_ERROR
+        }
+    };
+
+    ExitCode::from(exit_status)
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| CliError::Usage(format!("Missing value for {name}.")))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-w" | "--window" => {
+                let window = value(&arg)?;
+                options.window_size = parse_value(&arg, &window)?;
+                if options.window_size == 0 {
+                    return Err(invalid_value(&arg, &window));
+                }
+            }
+            "-e" | "--expected" => {
+                options.expected_blocks = Some(parse_value(&arg, &value(&arg)?)?)
+            }
+            "-b" | "--block" => {
+                options.block_type = match value(&arg)?.as_str() {
+                    "u64" => BlockType::U64,
+                    "u128" => BlockType::U128,
+                    "i128" => BlockType::I128,
+                    #[cfg(feature = "bigint")]
+                    "bigint" => BlockType::BigInt,
+                    #[cfg(not(feature = "bigint"))]
+                    "bigint" => {
+                        return Err(CliError::Usage(
+                            "Block type bigint requires the `bigint` feature.".to_owned(),
+                        ))
+                    }
+                    other => return Err(invalid_value(&arg, other)),
+                }
+            }
+            "-i" | "--impl" => {
+                options.implementation = match value(&arg)?.as_str() {
+                    "hash" => Implementation::Hash,
+                    "two-ptr" => Implementation::TwoPtr,
+                    "auto" => Implementation::Auto,
+                    other => return Err(invalid_value(&arg, other)),
+                }
+            }
+            "-n" | "--numbers" => {
+                options.numbers = match value(&arg)?.as_str() {
+                    "decimal" => NumberFormat::default(),
+                    "any" => NumberFormat::any(),
+                    other => return Err(invalid_value(&arg, other)),
+                }
+            }
+            "-s" | "--summands" => {
+                options.policy = match value(&arg)?.as_str() {
+                    "distinct-positions" => SummandPolicy::DistinctPositions,
+                    "distinct-values" => SummandPolicy::DistinctValues,
+                    other => return Err(invalid_value(&arg, other)),
+                }
+            }
+            "-r" | "--report" => options.report = true,
+            "-f" | "--format" => {
+                options.format = match value(&arg)?.as_str() {
+                    "text" => Format::Text,
+                    "jsonl" => Format::JsonLines,
+                    other => return Err(invalid_value(&arg, other)),
+                }
+            }
+            "-" => options.input = None,
+            flag if flag.starts_with('-') => {
+                return Err(CliError::Usage(format!("Unknown option {flag}.")))
+            }
+            _ if options.input.is_some() => {
+                return Err(CliError::Usage(
+                    "Only one input file is allowed.".to_owned(),
+                ))
+            }
+            _ => options.input = Some(arg),
+        }
+    }
+
+    Ok(Command::Validate(options))
+}
+
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value.parse().map_err(|_| invalid_value(name, value))
+}
+
+fn invalid_value(name: &str, value: &str) -> CliError {
+    CliError::Usage(format!("Invalid value {value:?} for {name}."))
+}
+
+fn open_and_run(options: &Options) -> Result<Outcome, CliError> {
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    match &options.input {
+        Some(path) => run(
+            options,
+            DecompressedReader::new(File::open(path)?)?,
+            &mut output,
+        ),
+        None => run(
+            options,
+            DecompressedReader::new(io::stdin().lock())?,
+            &mut output,
+        ),
+    }
+}
+
+fn run(
+    options: &Options,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<Outcome, CliError> {
+    match options.block_type {
+        BlockType::U64 => validate::<u64>(options, input, output),
+        BlockType::U128 => validate::<u128>(options, input, output),
+        BlockType::I128 => validate::<i128>(options, input, output),
+        #[cfg(feature = "bigint")]
+        BlockType::BigInt => validate::<tmmt::mine::BigBlock>(options, input, output),
+    }
+}
+
+fn validate<B>(
+    options: &Options,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<Outcome, CliError>
+where
+    B: Block + Hash + Ord + Clone + Integer + Display,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let mut blocks = BlockReader::with_parser(input, options.numbers.parser());
+
+    let initialization_blocks = blocks
+        .by_ref()
+        .take(options.window_size)
+        .collect::<Result<Vec<_>, _>>()?;
+    if initialization_blocks.len() < options.window_size {
+        return Err(CliError::TooShort(initialization_blocks.len()));
+    }
+
+    let mut mine = create_mine(options, initialization_blocks);
+    if options.format == Format::JsonLines {
+        return write_json_lines(options, &mut mine, blocks, output);
+    }
+
+    let mut invalid_blocks = 0;
+
+    while let Some(block) = blocks.next() {
+        if let Err(MineError::InvalidBlock(value, _)) = mine.try_extend_one(block?) {
+            invalid_blocks += 1;
+            // block numbers repeat once invalid blocks are skipped, lines don't
+            writeln!(output, "Invalid block on line {}: {value}", blocks.line())?;
+
+            if !options.report {
+                return Ok(Outcome::Invalid);
+            }
+        }
+    }
+
+    let valid_blocks = mine.total_blocks();
+    if options.report {
+        writeln!(
+            output,
+            "Read {} blocks: {valid_blocks} valid, {invalid_blocks} invalid.",
+            valid_blocks + invalid_blocks
+        )?;
+    } else {
+        writeln!(output, "All {valid_blocks} blocks are valid.")?;
+    }
+
+    if invalid_blocks == 0 {
+        Ok(Outcome::Valid)
+    } else {
+        Ok(Outcome::Invalid)
+    }
+}
+
+/// Validate the remaining `blocks` and write a JSON Lines record for each of them.
+fn write_json_lines<B, R>(
+    options: &Options,
+    mine: &mut CliMine<B>,
+    blocks: BlockReader<R, B, NumberParser>,
+    output: &mut impl Write,
+) -> Result<Outcome, CliError>
+where
+    B: Block + Hash + Ord + Clone + Integer + Display,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+    R: BufRead,
+{
+    let mut writer = JsonLinesWriter::new(output, Verbosity::AllBlocks);
+    let mut outcome = Outcome::Valid;
+
+    for block in blocks {
+        if writer.try_extend_one(mine, block?)?.is_err() {
+            outcome = Outcome::Invalid;
+            if !options.report {
+                break;
+            }
+        }
+    }
+
+    writer.finish(mine.total_blocks())?;
+    Ok(outcome)
+}
+
+fn create_mine<B>(options: &Options, initialization_blocks: Vec<B>) -> CliMine<B>
+where
+    B: Block + Hash + Ord + Clone,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let strategy = match options.implementation {
+        Implementation::Hash => Strategy::Hash,
+        Implementation::TwoPtr => Strategy::TwoPtr,
+        Implementation::Auto => {
+            Strategy::choose_for::<B>(initialization_blocks.len(), options.expected_blocks)
+        }
+    };
+    let mine = AdaptiveMine::with_window(initialization_blocks, strategy);
+    PolicyMine::with_policy(mine, options.policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../resources/test_input.txt");
+
+    fn args(args: &str) -> Result<Command, CliError> {
+        parse_args(args.split_whitespace().map(str::to_owned))
+    }
+
+    fn run_str(options: &Options, input: &str) -> (Result<Outcome, CliError>, String) {
+        let mut output = Vec::new();
+        let outcome = run(options, input.as_bytes(), &mut output);
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(args("").unwrap(), Command::Validate(Options::default()));
+        assert_eq!(args("-w 5 --help").unwrap(), Command::Help);
+        assert_eq!(
+            args("-w 5 --block i128 -i hash -s distinct-values -r -f jsonl -e 1000 -n any blocks.txt")
+                .unwrap(),
+            Command::Validate(Options {
+                window_size: 5,
+                block_type: BlockType::I128,
+                implementation: Implementation::Hash,
+                expected_blocks: Some(1000),
+                numbers: NumberFormat::any(),
+                policy: SummandPolicy::DistinctValues,
+                report: true,
+                format: Format::JsonLines,
+                input: Some("blocks.txt".to_owned()),
+            })
+        );
+
+        assert!(matches!(args("-w"), Err(CliError::Usage(_))));
+        assert!(matches!(args("-w five"), Err(CliError::Usage(_))));
+        assert!(matches!(args("-b u8"), Err(CliError::Usage(_))));
+        assert!(matches!(args("--verbose"), Err(CliError::Usage(_))));
+        assert!(matches!(args("a.txt b.txt"), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn first_invalid_block() {
+        for implementation in [
+            Implementation::Hash,
+            Implementation::TwoPtr,
+            Implementation::Auto,
+        ] {
+            let options = Options {
+                window_size: 5,
+                implementation,
+                ..Options::default()
+            };
+
+            let (outcome, output) = run_str(&options, EXAMPLE);
+            assert_eq!(outcome.unwrap(), Outcome::Invalid);
+            assert_eq!(output, "Invalid block on line 15: 127\n");
+
+            let (outcome, output) = run_str(&options, &EXAMPLE[..EXAMPLE.find("127").unwrap()]);
+            assert_eq!(outcome.unwrap(), Outcome::Valid);
+            assert_eq!(output, "All 14 blocks are valid.\n");
+        }
+    }
+
+    #[test]
+    fn window_sizes() {
+        let mut input: String = (1..=75).map(|block| format!("{block}\n")).collect();
+        input.push_str("149\n1\n");
+
+        // with a window of 33 block 66 is smaller than 33 + 34, the smallest sum
+        for (window_size, line, value) in [(33, 66, 66), (75, 77, 1)] {
+            let options = Options {
+                window_size,
+                ..Options::default()
+            };
+            let (outcome, output) = run_str(&options, &input);
+            assert_eq!(outcome.unwrap(), Outcome::Invalid);
+            assert_eq!(output, format!("Invalid block on line {line}: {value}\n"));
+        }
+    }
+
+    #[test]
+    fn report() {
+        let options = Options {
+            window_size: 5,
+            block_type: BlockType::U64,
+            report: true,
+            ..Options::default()
+        };
+
+        let (outcome, output) = run_str(&options, EXAMPLE);
+        assert_eq!(outcome.unwrap(), Outcome::Invalid);
+        assert_eq!(
+            output,
+            "Invalid block on line 15: 127\n\
+            Invalid block on line 18: 277\n\
+            Invalid block on line 19: 309\n\
+            Invalid block on line 20: 576\n\
+            Read 20 blocks: 16 valid, 4 invalid.\n"
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let options = Options {
+            window_size: 5,
+            implementation: Implementation::TwoPtr,
+            format: Format::JsonLines,
+            ..Options::default()
+        };
+
+        let (outcome, output) = run_str(&options, "35\n20\n15\n25\n47\n40\n127\n62\n");
+        assert_eq!(outcome.unwrap(), Outcome::Invalid);
+        assert_eq!(
+            output,
+            r#"{"type":"block","block":6,"value":40,"status":"accepted","pair":[15,25]}
+{"type":"block","block":7,"value":127,"status":"rejected"}
+{"type":"summary","accepted":1,"rejected":1,"total_blocks":6}
+"#
+        );
+    }
+
+    #[test]
+    fn input_errors() {
+        let options = Options {
+            window_size: 5,
+            ..Options::default()
+        };
+
+        let (outcome, _) = run_str(&options, "1\n2\n");
+        assert!(matches!(outcome, Err(CliError::TooShort(2))));
+
+        let (outcome, _) = run_str(&options, "1\n2\n3\n4\n5\nsix\n");
+        assert!(matches!(
+            outcome,
+            Err(CliError::Parse(ParseError { line: 6, .. }))
+        ));
+        let (outcome, _) = run_str(&options, "1\n2\n3\n4\n5\n0x6\n");
+        assert!(matches!(
+            outcome,
+            Err(CliError::Parse(ParseError { line: 6, .. }))
+        ));
+
+        let options = Options {
+            window_size: 5,
+            numbers: NumberFormat::any(),
+            ..Options::default()
+        };
+        let (outcome, output) = run_str(&options, "0x23\n20\n1.5e1\n25\n4_7\n40\n");
+        assert_eq!(outcome.unwrap(), Outcome::Valid);
+        assert_eq!(output, "All 6 blocks are valid.\n");
+
+        assert!(matches!(args("-w 0"), Err(CliError::Usage(_))));
+    }
+}
@@ -7,6 +7,11 @@ use thiserror::Error;
 pub trait Block: Eq + Add<Output = Self> + Sized {}
 impl<T> Block for T where T: Eq + Add<Output = Self> + Sized {}
 
+/// Arbitrary precision [Block], for streams with values that don't fit in `u128`.
+/// Works with every [Mine] in the crate.
+#[cfg(feature = "bigint")]
+pub type BigBlock = num_bigint::BigUint;
+
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum MineError<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
     #[error(
@@ -19,32 +24,108 @@ pub enum MineError<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
         A block is valid iff it is the sum of any two blocks in the previous: {}.",
         VALIDATION_WINDOW_SIZE
     )]
-    InvalidBlock(B, usize),
+    InvalidBlock(B, u64),
 }
 
+/// Validation window size of a mine whose window is sized at runtime by the number of its
+/// initialization blocks, see [AdaptiveMine::with_window]. Such a mine validates blocks
+/// like any other, but the window size in its [MineError] messages is 0, and types taking
+/// the window size from the mine, like [LedgerWriter](crate::ledger::LedgerWriter), can't
+/// be used with it.
+///
+/// [AdaptiveMine::with_window]: crate::adaptive_mine::AdaptiveMine::with_window
+pub const RUNTIME_WINDOW_SIZE: usize = 0;
+
 /// Responsible for mining new [Blocks](Block).
 /// A new block is valid [iff](https://en.wikipedia.org/wiki/If_and_only_if) it's the
 /// sum of any two blocks in the previous [VALIDATION_WINDOW_SIZE] blocks.
 ///
 /// [VALIDATION_WINDOW_SIZE]: Mine<VALIDATION_WINDOW_SIZE>s
 pub trait Mine<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
+    /// Error returned when the mine can't be created or extended.
+    /// Every implementation must be able to report a [MineError], but can
+    /// extend it with its own failure modes.
+    type Error: From<MineError<VALIDATION_WINDOW_SIZE, B>>;
+
     /// Create a new mine with given `initialization_blocks`.
     /// No validation is performed on the blocks in the initialization sequence.
-    fn new(initialization_blocks: [B; VALIDATION_WINDOW_SIZE]) -> Self;
+    fn new(initialization_blocks: [B; VALIDATION_WINDOW_SIZE]) -> Self
+    where
+        Self: Sized,
+    {
+        Self::resume(initialization_blocks, VALIDATION_WINDOW_SIZE as u64)
+    }
+
+    /// Recreate a mine that had `total_blocks` blocks added to it, from the
+    /// `validation_blocks` it held, in arrival order. Block numbers continue from
+    /// `total_blocks`, see [Checkpoint](crate::checkpoint::Checkpoint).
+    /// No validation is performed on the validation blocks.
+    /// # Panics
+    /// If `total_blocks` is less than [VALIDATION_WINDOW_SIZE].
+    ///
+    /// [VALIDATION_WINDOW_SIZE]: Mine<VALIDATION_WINDOW_SIZE>
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self;
     /// Try to extend the [Mine] with all the items from the
     /// `blocks` iterator. The method is successful if all
     /// the blocks are successfully added, or the iterator is empty. Otherwise the
     /// error [MineError::InvalidBlock] of the first invalid block
     /// is returned. **IMPORTANT:** Blocks prior to the invalid block are still added
     /// to the mine.
-    fn try_extend_one(&mut self, new_block: B) -> Result<(), MineError<VALIDATION_WINDOW_SIZE, B>>;
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error>;
+
+    /// Check if `new_block` would be accepted by [Mine::try_extend_one]
+    /// without adding it to the mine. Returns the same error
+    /// [Mine::try_extend_one] would.
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error>;
+
+    /// Blocks currently used for validation, in the order they were added to the mine.
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a;
+
+    /// Number of blocks added to the mine so far, initialization blocks included.
+    fn total_blocks(&self) -> u64;
+
+    /// Blocks currently used for validation, in ascending order.
+    /// `None` if the implementation doesn't keep a sorted window.
+    fn ordered_validation_blocks(&self) -> Option<&[B]> {
+        None
+    }
+
+    /// Number of distinct sums of two blocks in the validation window.
+    /// `None` if the implementation doesn't keep track of pair sums.
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        None
+    }
+
+    /// Two blocks in the validation window that sum up to `new_block`.
+    /// `None` if there is no such pair, or if the implementation can't tell which
+    /// pair it is.
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)> {
+        let _ = new_block;
+        None
+    }
+
+    /// Same as [Mine::try_extend_one], also returning the pair justifying `new_block` as
+    /// [Mine::justifying_pair] would. Implementations that can tell the pair find it with
+    /// the same search that validates the block.
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, Self::Error>
+    where
+        B: Clone,
+    {
+        let pair = self
+            .justifying_pair(&new_block)
+            .map(|(first, second)| (first.clone(), second.clone()));
+        self.try_extend_one(new_block)?;
+        Ok(pair)
+    }
 
     /// Same as [Mine::new] except if the initialization sequence fail to convert
     /// to the desired array [MineError::InvalidInitializationSequenceLen]
     /// is returned.
     fn try_new(
         initialization_blocks: impl TryInto<[B; VALIDATION_WINDOW_SIZE]>,
-    ) -> Result<Self, MineError<VALIDATION_WINDOW_SIZE, B>>
+    ) -> Result<Self, Self::Error>
     where
         Self: Sized,
     {
@@ -62,10 +143,7 @@ pub trait Mine<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
     /// error [MineError::InvalidBlock] of the first invalid block
     /// is returned. **IMPORTANT:** Blocks prior to the invalid block are still added
     /// to the mine.
-    fn try_extend(
-        &mut self,
-        blocks: impl IntoIterator<Item = B>,
-    ) -> Result<(), MineError<VALIDATION_WINDOW_SIZE, B>> {
+    fn try_extend(&mut self, blocks: impl IntoIterator<Item = B>) -> Result<(), Self::Error> {
         for block in blocks {
             self.try_extend_one(block)?
         }
@@ -78,22 +156,21 @@ pub trait Mine<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
     ///
     /// # Errors
     /// - If the `blocks` iterator length is less than [VALIDATION_WINDOW_SIZE] then
-    /// [MineError::InvalidInitializationSequenceLen] is returned.
+    ///   [MineError::InvalidInitializationSequenceLen] is returned.
     /// - If any remaining element can't be validated [MineError::InvalidBlock] is returned.
+    /// - Any other [Mine::Error] of the implementation is passed through.
     ///
     /// [VALIDATION_WINDOW_SIZE]: Mine<VALIDATION_WINDOW_SIZE>
-    fn try_create_and_extend(
-        blocks: impl IntoIterator<Item = B>,
-    ) -> Result<(), MineError<VALIDATION_WINDOW_SIZE, B>>
+    fn try_create_and_extend(blocks: impl IntoIterator<Item = B>) -> Result<(), Self::Error>
     where
         Self: Sized,
     {
         let (initialization_blocks, remaining_blocks) =
             take_with_remainder(blocks.into_iter(), VALIDATION_WINDOW_SIZE);
 
-        let initialization_blocks = initialization_blocks
-            .try_into()
-            .map_err(|_| MineError::InvalidInitializationSequenceLen)?;
+        let initialization_blocks = initialization_blocks.try_into().map_err(|_| {
+            MineError::<VALIDATION_WINDOW_SIZE, B>::InvalidInitializationSequenceLen
+        })?;
 
         let mut mine = Self::new(initialization_blocks);
 
@@ -101,6 +178,32 @@ pub trait Mine<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
     }
 }
 
+/// Validate `new_block` with [Mine::peek], `record` it with the result of validation,
+/// then add it to the `mine` if it's accepted. `record` gets the mine as it was before
+/// the block.
+///
+/// The outer result is the failure to record, the inner one is the result of validation.
+/// A block that failed to be recorded isn't added to the mine, so a record kept next to
+/// the mine never falls behind it. Every writer in the crate recording the blocks it
+/// validates goes through this function.
+pub fn try_extend_recorded<const VALIDATION_WINDOW_SIZE: usize, B, M, E>(
+    mine: &mut M,
+    new_block: B,
+    record: impl FnOnce(&M, &B, Result<(), &M::Error>) -> Result<(), E>,
+) -> Result<Result<(), M::Error>, E>
+where
+    B: Block,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+    let validation = mine.peek(&new_block);
+    record(mine, &new_block, validation.as_ref().map(|_| ()))?;
+
+    Ok(match validation {
+        Ok(()) => mine.try_extend_one(new_block),
+        Err(error) => Err(error),
+    })
+}
+
 /// Take n items from the iterator, or less if the iterator has less items.
 /// Return the taken items in a Vec. If the iterator was empty an empty vector is returned.
 fn take_with_remainder<T, I: Iterator<Item = T>>(mut iter: I, n: usize) -> (Vec<T>, Fuse<I>) {
@@ -122,3 +225,80 @@ fn take_with_remainder<T, I: Iterator<Item = T>>(mut iter: I, n: usize) -> (Vec<
 
     (taken, remainder)
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::two_ptr_mine::TwoPtrMine;
+
+    const CAP: u32 = 100;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    enum CappedError<const VALIDATION_WINDOW_SIZE: usize> {
+        #[error(transparent)]
+        Mine(#[from] MineError<VALIDATION_WINDOW_SIZE, u32>),
+        #[error("Block {0} is larger than {}", CAP)]
+        TooLarge(u32),
+    }
+
+    /// Mine with a failure mode of its own.
+    struct CappedMine<const VALIDATION_WINDOW_SIZE: usize>(TwoPtrMine<VALIDATION_WINDOW_SIZE, u32>);
+
+    impl<const VALIDATION_WINDOW_SIZE: usize> Mine<VALIDATION_WINDOW_SIZE, u32>
+        for CappedMine<VALIDATION_WINDOW_SIZE>
+    {
+        type Error = CappedError<VALIDATION_WINDOW_SIZE>;
+
+        fn resume(validation_blocks: [u32; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+            Self(TwoPtrMine::resume(validation_blocks, total_blocks))
+        }
+
+        fn try_extend_one(&mut self, new_block: u32) -> Result<(), Self::Error> {
+            self.peek(&new_block)?;
+            Ok(self.0.try_extend_one(new_block)?)
+        }
+
+        fn peek(&self, new_block: &u32) -> Result<(), Self::Error> {
+            if *new_block > CAP {
+                return Err(CappedError::TooLarge(*new_block));
+            }
+            Ok(self.0.peek(new_block)?)
+        }
+
+        fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a u32>
+        where
+            u32: 'a,
+        {
+            self.0.validation_blocks()
+        }
+
+        fn total_blocks(&self) -> u64 {
+            self.0.total_blocks()
+        }
+    }
+
+    #[test]
+    fn custom_error() {
+        assert_eq!(
+            CappedMine::<5>::try_create_and_extend([35, 20, 15, 25, 47, 40, 62, 55, 65, 95]),
+            Ok(())
+        );
+        assert_eq!(
+            CappedMine::<5>::try_create_and_extend([35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102]),
+            Err(CappedError::TooLarge(102))
+        );
+        assert_eq!(
+            CappedMine::<5>::try_create_and_extend([35, 20, 15, 25, 47, 1]),
+            Err(CappedError::Mine(MineError::InvalidBlock(1, 6)))
+        );
+        assert!(matches!(
+            CappedMine::<5>::try_new(vec![35, 20, 15]),
+            Err(CappedError::Mine(
+                MineError::InvalidInitializationSequenceLen
+            ))
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{
+    binary::{BinaryError, BlockFileReader, FixedWidthBlock},
+    mine::{Block, Mine},
+    parse::{self, BlockParser, FromStrParser, IngestError, LineBlocks, ParseError},
+};
+
+use std::{fmt::Display, fs::File, io, marker::PhantomData, path::Path, str::FromStr};
+
+use memmap2::Mmap;
+
+/// Block file mapped into memory, in the text format of [BlockReader](crate::parse::BlockReader)
+/// or the [binary](crate::binary) format.
+///
+/// Blocks are parsed straight from the mapped bytes, without copying or allocating per line.
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Map the file at `path` into memory.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated while it's mapped, by this or any
+    /// other process. See [Mmap::map].
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let map = Mmap::map(&file)?;
+
+        Ok(Self { map })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Iterator over the blocks of a text file.
+    pub fn text_blocks<B>(&self) -> TextBlocks<'_, B>
+    where
+        B: FromStr,
+        B::Err: Display,
+    {
+        TextBlocks::new(&self.map)
+    }
+
+    /// Iterator over the blocks of a text file, parsed by `parser`.
+    pub fn text_blocks_with<B, P>(&self, parser: P) -> TextBlocks<'_, B, P>
+    where
+        P: BlockParser<B>,
+    {
+        TextBlocks::with_parser(&self.map, parser)
+    }
+
+    /// Iterator over the blocks of a binary file. The checksum is checked by
+    /// [BlockFileReader::finish].
+    pub fn binary_blocks<B>(&self) -> Result<BlockFileReader<&[u8], B>, BinaryError>
+    where
+        B: FixedWidthBlock,
+    {
+        BlockFileReader::new(&self.map[..])
+    }
+
+    /// Same as [Ingest::try_create_and_ingest](crate::parse::Ingest::try_create_and_ingest)
+    /// for a text file.
+    pub fn try_create_and_ingest<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &self,
+    ) -> Result<(), IngestError<M::Error>>
+    where
+        B: Block + FromStr,
+        B::Err: Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        self.try_create_and_ingest_with::<VALIDATION_WINDOW_SIZE, B, M>(FromStrParser)
+    }
+
+    /// Same as [MappedFile::try_create_and_ingest] with the blocks parsed by `parser`.
+    pub fn try_create_and_ingest_with<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &self,
+        parser: impl BlockParser<B>,
+    ) -> Result<(), IngestError<M::Error>>
+    where
+        B: Block,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        parse::create_and_extend_from_blocks::<VALIDATION_WINDOW_SIZE, B, M>(
+            self.text_blocks_with(parser),
+        )
+    }
+}
+
+/// Iterator over the blocks in text held in memory.
+/// Follows the format of [BlockReader](crate::parse::BlockReader), blocks are parsed by a
+/// [BlockParser], [FromStrParser] by default.
+#[derive(Debug, Clone)]
+pub struct TextBlocks<'a, B, P = FromStrParser> {
+    bytes: &'a [u8],
+    parser: P,
+    /// Offset of the next line.
+    position: usize,
+    /// Number of lines read so far.
+    line: usize,
+    _block: PhantomData<fn() -> B>,
+}
+
+impl<'a, B> TextBlocks<'a, B> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_parser(bytes, FromStrParser)
+    }
+}
+
+impl<'a, B, P> TextBlocks<'a, B, P> {
+    pub fn with_parser(bytes: &'a [u8], parser: P) -> Self {
+        Self {
+            bytes,
+            parser,
+            position: 0,
+            line: 0,
+            _block: PhantomData,
+        }
+    }
+
+    /// Line of the last item returned by the iterator, starting from 1.
+    /// Zero if nothing has been read yet.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl<B, P> Iterator for TextBlocks<'_, B, P>
+where
+    P: BlockParser<B>,
+{
+    type Item = Result<B, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.bytes.len() {
+            let line_offset = self.position;
+            let rest = &self.bytes[line_offset..];
+            let line = match rest.iter().position(|byte| *byte == b'\n') {
+                Some(end) => &rest[..=end],
+                None => rest,
+            };
+
+            self.position += line.len();
+            self.line += 1;
+
+            let block = parse::parse_line(line, self.line, line_offset as u64, &mut self.parser);
+            if let Some(block) = block {
+                return Some(block);
+            }
+        }
+
+        None
+    }
+}
+
+impl<B, P> LineBlocks<B> for TextBlocks<'_, B, P>
+where
+    P: BlockParser<B>,
+{
+    fn line(&self) -> usize {
+        self.line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::io::{BufWriter, Write};
+
+    use super::*;
+
+    use crate::{
+        binary::BlockFileWriter,
+        hash_mine::HashMine,
+        mine::MineError,
+        number::NumberFormat,
+        parse::{BlockReader, ParseErrorKind},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    /// `window_size` initialization blocks and `count` valid blocks followed by an invalid block.
+    fn generate(window_size: u64, count: u64) -> impl Iterator<Item = u64> {
+        // repeats 0, 0, 1, 2, .., so every block is the sum of the block it replaces and a zero
+        (0..window_size + count)
+            .map(move |i| (i % window_size).saturating_sub(1))
+            .chain([10 * window_size])
+    }
+
+    fn map(file: &tempfile::NamedTempFile) -> MappedFile {
+        // SAFETY: the temp file is only written before it is mapped
+        unsafe { MappedFile::open(file.path()).unwrap() }
+    }
+
+    #[test]
+    fn text() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&mut file);
+            writeln!(writer, "# generated").unwrap();
+            for block in generate(100, 10_000) {
+                writeln!(writer, " {block}\r").unwrap();
+            }
+        }
+
+        let mapped = map(&file);
+        let blocks: Vec<Result<u64, _>> = mapped.text_blocks().collect();
+        let read: Vec<Result<u64, _>> = BlockReader::new(mapped.bytes()).collect();
+        assert_eq!(blocks, read);
+        assert_eq!(blocks.len(), 10_101);
+
+        assert_eq!(
+            mapped.try_create_and_ingest::<100, u64, HashMine<100, u64>>(),
+            Err(IngestError::Mine {
+                line: Some(10_102),
+                source: MineError::InvalidBlock(1000, 10_101)
+            })
+        );
+    }
+
+    #[test]
+    fn text_errors() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"1\n2\n\n 3x").unwrap();
+
+        let mapped = map(&file);
+        let mut blocks = mapped.text_blocks::<u32>();
+        assert_eq!(blocks.next(), Some(Ok(1)));
+        assert_eq!(blocks.next(), Some(Ok(2)));
+        assert!(matches!(
+            blocks.next(),
+            Some(Err(ParseError {
+                line: 4,
+                byte_offset: 6,
+                kind: ParseErrorKind::InvalidBlock { .. }
+            }))
+        ));
+        assert_eq!(blocks.next(), None);
+
+        let empty = tempfile::NamedTempFile::new().unwrap();
+        assert_eq!(map(&empty).text_blocks::<u32>().next(), None);
+    }
+
+    #[test]
+    fn text_parser() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(
+            b"0x23
+20
+1.5e1
+25
+4_7
+40
+0x7F
+",
+        )
+        .unwrap();
+
+        let mapped = map(&file);
+        assert_eq!(
+            mapped.try_create_and_ingest_with::<5, u64, HashMine<5, u64>>(
+                NumberFormat::any().parser()
+            ),
+            Err(IngestError::Mine {
+                line: Some(7),
+                source: MineError::InvalidBlock(127, 7)
+            })
+        );
+    }
+
+    #[test]
+    fn binary() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = BlockFileWriter::new(BufWriter::new(file), 100).unwrap();
+        writer.write_blocks(generate(100, 10_000)).unwrap();
+        let file = writer.finish().unwrap().into_inner().unwrap();
+
+        let mapped = map(&file);
+        let mut blocks = mapped.binary_blocks::<u64>().unwrap();
+        assert_eq!(blocks.header().blocks, 10_101);
+
+        let result = TwoPtrMine::<100, u64>::try_create_and_extend(&mut blocks);
+        assert_eq!(blocks.finish().map(|header| header.window_size), Ok(100));
+        assert_eq!(result, Err(MineError::InvalidBlock(1000, 10_101)));
+    }
+}
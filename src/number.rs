@@ -0,0 +1,562 @@
+use crate::parse::BlockParser;
+
+use std::fmt::{self, Display};
+
+use thiserror::Error;
+
+/// Upper limit on the number of digits of a scientific integer, so a huge exponent
+/// can't exhaust memory.
+const MAX_DIGITS: usize = 10_000;
+
+/// Syntax of an integer block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    /// `123`, always enabled.
+    Decimal,
+    /// `0x7b` or `0X7B`.
+    Hex,
+    /// `1.23e2` or `123E0`, the value must be an integer.
+    Scientific,
+}
+
+impl Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Decimal => "decimal",
+            Self::Hex => "hex",
+            Self::Scientific => "scientific",
+        })
+    }
+}
+
+/// Where the [Syntax] of a block is detected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Detection {
+    /// Every block can use any enabled syntax.
+    #[default]
+    PerLine,
+    /// The syntax of the first valid block is used for the whole input.
+    PerFile,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    #[error("no digits")]
+    Empty,
+    #[error("invalid digit {0:?}")]
+    InvalidDigit(char),
+    #[error("digit separator `_` must be between two digits")]
+    MisplacedSeparator,
+    #[error("{0} numbers are not enabled")]
+    DisabledSyntax(Syntax),
+    #[error("{found} number in an input of {expected} numbers")]
+    MixedSyntax { expected: Syntax, found: Syntax },
+    #[error("not an integer")]
+    NotAnInteger,
+    #[error("doesn't fit in {type_name}, valid values are {range}")]
+    OutOfRange {
+        type_name: &'static str,
+        range: String,
+    },
+}
+
+/// Integer [Block](crate::mine::Block) that can be parsed in any [Syntax].
+/// Implemented for the primitive integers, and [BigBlock](crate::mine::BigBlock)
+/// with the `bigint` feature.
+pub trait Integer: Sized {
+    /// Parse the `digits` of a number in the given `radix`, negated if `negative`.
+    /// `digits` are not empty and hold only valid digits of the `radix`.
+    fn from_digits(negative: bool, digits: &str, radix: u32) -> Result<Self, NumberError>;
+}
+
+macro_rules! unsigned_integer {
+    ($($block:ty),*) => {
+        $(
+            impl Integer for $block {
+                fn from_digits(
+                    negative: bool,
+                    digits: &str,
+                    radix: u32,
+                ) -> Result<Self, NumberError> {
+                    match <$block>::from_str_radix(digits, radix) {
+                        Ok(0) => Ok(0),
+                        Ok(magnitude) if !negative => Ok(magnitude),
+                        _ => Err(out_of_range::<$block>(<$block>::MIN, <$block>::MAX)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! signed_integer {
+    ($($block:ty: $unsigned:ty),*) => {
+        $(
+            impl Integer for $block {
+                fn from_digits(
+                    negative: bool,
+                    digits: &str,
+                    radix: u32,
+                ) -> Result<Self, NumberError> {
+                    let out_of_range = || out_of_range::<$block>(<$block>::MIN, <$block>::MAX);
+                    let magnitude =
+                        <$unsigned>::from_str_radix(digits, radix).map_err(|_| out_of_range())?;
+
+                    if negative {
+                        if magnitude > <$block>::MIN.unsigned_abs() {
+                            return Err(out_of_range());
+                        }
+                        // wraps for MIN, which is its own negation
+                        Ok((magnitude as $block).wrapping_neg())
+                    } else {
+                        <$block>::try_from(magnitude).map_err(|_| out_of_range())
+                    }
+                }
+            }
+        )*
+    };
+}
+
+unsigned_integer!(u8, u16, u32, u64, u128, usize);
+signed_integer!(i8: u8, i16: u16, i32: u32, i64: u64, i128: u128, isize: usize);
+
+#[cfg(feature = "bigint")]
+impl Integer for crate::mine::BigBlock {
+    fn from_digits(negative: bool, digits: &str, radix: u32) -> Result<Self, NumberError> {
+        let magnitude =
+            Self::parse_bytes(digits.as_bytes(), radix).expect("digits are valid in the radix");
+
+        if negative && magnitude != Self::default() {
+            Err(NumberError::OutOfRange {
+                type_name: "BigBlock",
+                range: "0 or more".to_owned(),
+            })
+        } else {
+            Ok(magnitude)
+        }
+    }
+}
+
+fn out_of_range<B: Display>(min: B, max: B) -> NumberError {
+    NumberError::OutOfRange {
+        type_name: std::any::type_name::<B>(),
+        range: format!("{min} to {max}"),
+    }
+}
+
+/// Set of integer [Syntaxes](Syntax) accepted for blocks. Only decimal by default.
+///
+/// Every syntax accepts a leading `+` or `-` sign.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumberFormat {
+    hex: bool,
+    scientific: bool,
+    underscores: bool,
+    detection: Detection,
+}
+
+impl NumberFormat {
+    /// Accept every syntax, and `_` digit separators.
+    pub fn any() -> Self {
+        Self {
+            hex: true,
+            scientific: true,
+            underscores: true,
+            detection: Detection::PerLine,
+        }
+    }
+
+    pub fn with_hex(self, hex: bool) -> Self {
+        Self { hex, ..self }
+    }
+
+    pub fn with_scientific(self, scientific: bool) -> Self {
+        Self { scientific, ..self }
+    }
+
+    /// Accept `_` digit separators, like `1_000_000`.
+    pub fn with_underscores(self, underscores: bool) -> Self {
+        Self {
+            underscores,
+            ..self
+        }
+    }
+
+    pub fn with_detection(self, detection: Detection) -> Self {
+        Self { detection, ..self }
+    }
+
+    fn is_enabled(&self, syntax: Syntax) -> bool {
+        match syntax {
+            Syntax::Decimal => true,
+            Syntax::Hex => self.hex,
+            Syntax::Scientific => self.scientific,
+        }
+    }
+
+    /// Parser for a single input, see [NumberParser].
+    pub fn parser(self) -> NumberParser {
+        NumberParser {
+            format: self,
+            input_syntax: None,
+            digits: String::new(),
+        }
+    }
+}
+
+/// Parses the blocks of a single input in a [NumberFormat]. Use it with
+/// [BlockReader::with_parser](crate::parse::BlockReader::with_parser).
+#[derive(Clone, Debug)]
+pub struct NumberParser {
+    format: NumberFormat,
+    /// Syntax of the first valid block, used with [Detection::PerFile].
+    input_syntax: Option<Syntax>,
+    /// Holds the digits of the number being parsed.
+    digits: String,
+}
+
+impl NumberParser {
+    pub fn parse<B: Integer>(&mut self, value: &str) -> Result<B, NumberError> {
+        let (negative, unsigned) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value),
+        };
+
+        let (syntax, number) = if let Some(hex) = unsigned
+            .strip_prefix("0x")
+            .or_else(|| unsigned.strip_prefix("0X"))
+        {
+            (Syntax::Hex, hex)
+        } else if unsigned.contains(['e', 'E']) {
+            (Syntax::Scientific, unsigned)
+        } else {
+            (Syntax::Decimal, unsigned)
+        };
+
+        if !self.format.is_enabled(syntax) {
+            return Err(NumberError::DisabledSyntax(syntax));
+        }
+        let per_file = self.format.detection == Detection::PerFile;
+        if let Some(expected) = self.input_syntax.filter(|_| per_file) {
+            if expected != syntax {
+                return Err(NumberError::MixedSyntax {
+                    expected,
+                    found: syntax,
+                });
+            }
+        }
+
+        self.digits.clear();
+        let radix = match syntax {
+            Syntax::Decimal => {
+                self.push_digits(number, 10)?;
+                10
+            }
+            Syntax::Hex => {
+                self.push_digits(number, 16)?;
+                16
+            }
+            Syntax::Scientific => {
+                self.push_scientific(number)?;
+                10
+            }
+        };
+
+        let block = B::from_digits(negative, &self.digits, radix)?;
+        // only a valid block sets the syntax of the input
+        if per_file && self.input_syntax.is_none() {
+            self.input_syntax = Some(syntax);
+        }
+        Ok(block)
+    }
+
+    /// Push the `digits` in the `radix` without separators. Fails if there are no digits.
+    fn push_digits(&mut self, digits: &str, radix: u32) -> Result<(), NumberError> {
+        let start = self.digits.len();
+        let mut previous = None;
+        let mut chars = digits.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c == '_' && self.format.underscores {
+                let between_digits = previous.is_some_and(|previous: char| previous != '_')
+                    && chars.peek().is_some_and(|next| next.is_digit(radix));
+                if !between_digits {
+                    return Err(NumberError::MisplacedSeparator);
+                }
+            } else if c.is_digit(radix) {
+                self.digits.push(c);
+            } else {
+                return Err(NumberError::InvalidDigit(c));
+            }
+            previous = Some(c);
+        }
+
+        if self.digits.len() == start {
+            return Err(NumberError::Empty);
+        }
+        Ok(())
+    }
+
+    /// Push the decimal digits of a scientific integer, like `1.5e3`.
+    fn push_scientific(&mut self, number: &str) -> Result<(), NumberError> {
+        let (mantissa, exponent) = number
+            .split_once(['e', 'E'])
+            .expect("scientific numbers have an exponent");
+
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        self.push_digits(integer, 10)?;
+        let fraction_len = if fraction.is_empty() {
+            0
+        } else {
+            let start = self.digits.len();
+            self.push_digits(fraction, 10)?;
+            self.digits.len() - start
+        };
+
+        let (exponent_negative, exponent) = match exponent.as_bytes().first() {
+            Some(b'-') => (true, &exponent[1..]),
+            Some(b'+') => (false, &exponent[1..]),
+            _ => (false, exponent),
+        };
+        if exponent.is_empty() {
+            return Err(NumberError::Empty);
+        }
+        if let Some(c) = exponent.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(NumberError::InvalidDigit(c));
+        }
+        // too large exponents are out of range anyway
+        let exponent = exponent.parse::<i64>().unwrap_or(i64::MAX);
+        let exponent = if exponent_negative {
+            -exponent
+        } else {
+            exponent
+        };
+
+        // value is digits * 10^shift
+        let shift = exponent.saturating_sub(fraction_len as i64);
+        let significant = self.digits.trim_start_matches('0').len();
+        if significant == 0 {
+            self.digits.replace_range(.., "0");
+        } else if shift >= 0 {
+            if significant as i64 > MAX_DIGITS as i64 - shift {
+                return Err(NumberError::OutOfRange {
+                    type_name: "a block",
+                    range: format!("at most {MAX_DIGITS} digits long"),
+                });
+            }
+            self.digits.extend(std::iter::repeat_n('0', shift as usize));
+        } else {
+            let dropped = shift.unsigned_abs().min(self.digits.len() as u64) as usize;
+            let kept = self.digits.len() - dropped;
+            if self.digits[kept..].bytes().any(|digit| digit != b'0') {
+                return Err(NumberError::NotAnInteger);
+            }
+            self.digits.truncate(kept);
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: Integer> BlockParser<B> for NumberParser {
+    type Error = NumberError;
+
+    fn parse_block(&mut self, value: &str) -> Result<B, Self::Error> {
+        self.parse(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::Mine,
+        parse::{BlockReader, ParseError, ParseErrorKind},
+    };
+
+    fn parse<B: Integer>(format: NumberFormat, value: &str) -> Result<B, NumberError> {
+        format.parser().parse(value)
+    }
+
+    #[test]
+    fn syntaxes() {
+        let any = NumberFormat::any();
+
+        assert_eq!(parse::<u32>(any, "123"), Ok(123));
+        assert_eq!(parse::<u32>(any, "+0x7b"), Ok(123));
+        assert_eq!(parse::<u32>(any, "0X7_B"), Ok(123));
+        assert_eq!(parse::<u32>(any, "1_2_3"), Ok(123));
+        assert_eq!(parse::<u32>(any, "1.23e2"), Ok(123));
+        assert_eq!(parse::<u32>(any, "12300E-2"), Ok(123));
+        assert_eq!(parse::<u32>(any, "0.0e5"), Ok(0));
+        assert_eq!(parse::<u32>(any, "1e+3"), Ok(1000));
+        assert_eq!(parse::<i32>(any, "-1_000"), Ok(-1000));
+        assert_eq!(parse::<i32>(any, "-0x10"), Ok(-16));
+        assert_eq!(parse::<i128>(any, "-1.5e3"), Ok(-1500));
+        assert_eq!(parse::<u8>(any, "-0"), Ok(0));
+
+        let decimal = NumberFormat::default();
+        assert_eq!(parse::<u32>(decimal, "+123"), Ok(123));
+        assert_eq!(
+            parse::<u32>(decimal, "0x7b"),
+            Err(NumberError::DisabledSyntax(Syntax::Hex))
+        );
+        assert_eq!(
+            parse::<u32>(decimal, "1e3"),
+            Err(NumberError::DisabledSyntax(Syntax::Scientific))
+        );
+        assert_eq!(
+            parse::<u32>(decimal, "1_000"),
+            Err(NumberError::InvalidDigit('_'))
+        );
+        assert_eq!(parse::<u32>(decimal.with_hex(true), "0xff"), Ok(255));
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        let any = NumberFormat::any();
+
+        assert_eq!(parse::<u32>(any, ""), Err(NumberError::Empty));
+        assert_eq!(parse::<u32>(any, "-"), Err(NumberError::Empty));
+        assert_eq!(parse::<u32>(any, "0x"), Err(NumberError::Empty));
+        assert_eq!(parse::<u32>(any, "1e"), Err(NumberError::Empty));
+        assert_eq!(
+            parse::<u32>(any, "12a"),
+            Err(NumberError::InvalidDigit('a'))
+        );
+        assert_eq!(
+            parse::<u32>(any, "0xfg"),
+            Err(NumberError::InvalidDigit('g'))
+        );
+        assert_eq!(
+            parse::<u32>(any, "--1"),
+            Err(NumberError::InvalidDigit('-'))
+        );
+        assert_eq!(
+            parse::<u32>(any, "_1"),
+            Err(NumberError::MisplacedSeparator)
+        );
+        assert_eq!(
+            parse::<u32>(any, "1_"),
+            Err(NumberError::MisplacedSeparator)
+        );
+        assert_eq!(
+            parse::<u32>(any, "1__0"),
+            Err(NumberError::MisplacedSeparator)
+        );
+        assert_eq!(parse::<u32>(any, "1.5e0"), Err(NumberError::NotAnInteger));
+        assert_eq!(parse::<u32>(any, "15e-1"), Err(NumberError::NotAnInteger));
+        assert_eq!(
+            parse::<u32>(any, "1e1x"),
+            Err(NumberError::InvalidDigit('x'))
+        );
+    }
+
+    #[test]
+    fn range() {
+        let any = NumberFormat::any();
+
+        assert_eq!(parse::<u8>(any, "255"), Ok(255));
+        assert_eq!(
+            parse::<u8>(any, "256"),
+            Err(NumberError::OutOfRange {
+                type_name: "u8",
+                range: "0 to 255".to_owned()
+            })
+        );
+        assert_eq!(
+            parse::<u64>(any, "-1").unwrap_err().to_string(),
+            "doesn't fit in u64, valid values are 0 to 18446744073709551615"
+        );
+        assert_eq!(parse::<i8>(any, "-128"), Ok(i8::MIN));
+        assert_eq!(parse::<i8>(any, "-0x80"), Ok(i8::MIN));
+        assert!(parse::<i8>(any, "128").is_err());
+        assert!(parse::<i8>(any, "-129").is_err());
+        assert_eq!(
+            parse::<i128>(any, "-1.70141183460469231731687303715884105728e38"),
+            Ok(i128::MIN)
+        );
+        assert!(parse::<u128>(any, "1e39").is_err());
+        assert!(parse::<u128>(any, "1e9999999999999999999").is_err());
+    }
+
+    #[test]
+    fn detection() {
+        let mut parser = NumberFormat::any()
+            .with_detection(Detection::PerFile)
+            .parser();
+
+        assert_eq!(parser.parse::<u32>("0x10"), Ok(16));
+        assert_eq!(parser.parse::<u32>("0x1e"), Ok(30));
+        assert_eq!(
+            parser.parse::<u32>("10"),
+            Err(NumberError::MixedSyntax {
+                expected: Syntax::Hex,
+                found: Syntax::Decimal
+            })
+        );
+
+        // invalid blocks don't set the syntax
+        let mut parser = NumberFormat::any()
+            .with_detection(Detection::PerFile)
+            .parser();
+        assert!(parser.parse::<u32>("0xg").is_err());
+        assert!(parser.parse::<u8>("1e3").is_err());
+        assert_eq!(parser.parse::<u32>("10"), Ok(10));
+        assert!(parser.parse::<u32>("0x10").is_err());
+
+        let mut parser = NumberFormat::any().parser();
+        assert_eq!(parser.parse::<u32>("0x10"), Ok(16));
+        assert_eq!(parser.parse::<u32>("10"), Ok(10));
+    }
+
+    #[test]
+    fn block_reader() {
+        let input = "0x23\n20\n1.5e1\n+25\n4_7\n40\n0x3E\n256\n";
+        let parser = NumberFormat::any().parser();
+        let blocks: Vec<Result<u8, _>> =
+            BlockReader::with_parser(input.as_bytes(), parser).collect();
+
+        assert_eq!(
+            blocks[7],
+            Err(ParseError {
+                line: 8,
+                byte_offset: 30,
+                kind: ParseErrorKind::InvalidBlock {
+                    value: "256".to_owned(),
+                    reason: "doesn't fit in u8, valid values are 0 to 255".to_owned()
+                }
+            })
+        );
+
+        let blocks: Vec<u8> = blocks
+            .into_iter()
+            .take(7)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(blocks, [35, 20, 15, 25, 47, 40, 62]);
+        assert_eq!(HashMine::<5, u8>::try_create_and_extend(blocks), Ok(()));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn bigint() {
+        use crate::mine::BigBlock;
+
+        let any = NumberFormat::any();
+        assert_eq!(
+            parse::<BigBlock>(any, "1e40"),
+            Ok(BigBlock::from(10u8).pow(40))
+        );
+        assert_eq!(
+            parse::<BigBlock>(any, "0x1_0000_0000_0000_0000_0000_0000_0000_0000"),
+            Ok(BigBlock::from(1u8) << 128)
+        );
+        assert!(parse::<BigBlock>(any, "-1").is_err());
+    }
+}
@@ -0,0 +1,442 @@
+use crate::mine::{Block, Mine};
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead},
+    marker::PhantomData,
+    str::{self, FromStr},
+};
+
+use thiserror::Error;
+
+/// Starts a comment that runs until the end of the line.
+const COMMENT: u8 = b'#';
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("Failed to parse line {line} at byte offset {byte_offset}: {kind}")]
+pub struct ParseError {
+    /// Line of the failure, starting from 1.
+    pub line: usize,
+    /// Offset of the failure from the start of the input, starting from 0.
+    pub byte_offset: u64,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    #[error("Input is not valid UTF-8.")]
+    InvalidUtf8,
+    #[error("Invalid block value {value:?}: {reason}.")]
+    InvalidBlock { value: String, reason: String },
+    #[error("Failed to read input: {0}.")]
+    Io(io::ErrorKind),
+}
+
+/// Parses the text of a single block.
+pub trait BlockParser<B> {
+    type Error: Display;
+
+    fn parse_block(&mut self, value: &str) -> Result<B, Self::Error>;
+}
+
+/// Parses blocks with their [FromStr] implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FromStrParser;
+
+impl<B> BlockParser<B> for FromStrParser
+where
+    B: FromStr,
+    B::Err: Display,
+{
+    type Error = B::Err;
+
+    fn parse_block(&mut self, value: &str) -> Result<B, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Iterator over the [Blocks](Block) in a text input. Every line holds at most one block.
+///
+/// - Whitespace around the block, including `\r` of CRLF line endings, is ignored.
+/// - Anything following a `#` is a comment and is ignored.
+/// - Lines without a block are skipped.
+///
+/// Blocks are parsed by a [BlockParser], [FromStrParser] by default.
+/// Lines are read into a single reused buffer, so parsing doesn't allocate
+/// unless `B` does. Reading stops after the first I/O error.
+#[derive(Debug)]
+pub struct BlockReader<R, B, P = FromStrParser> {
+    reader: R,
+    parser: P,
+    /// Holds the line being parsed.
+    buffer: Vec<u8>,
+    /// Number of lines read so far.
+    line: usize,
+    /// Number of bytes read so far.
+    byte_offset: u64,
+    done: bool,
+    _block: PhantomData<fn() -> B>,
+}
+
+impl<R: BufRead, B> BlockReader<R, B> {
+    pub fn new(reader: R) -> Self {
+        Self::with_parser(reader, FromStrParser)
+    }
+}
+
+impl<R, B, P> BlockReader<R, B, P> {
+    pub fn with_parser(reader: R, parser: P) -> Self {
+        Self {
+            reader,
+            parser,
+            buffer: Vec::new(),
+            line: 0,
+            byte_offset: 0,
+            done: false,
+            _block: PhantomData,
+        }
+    }
+
+    /// Line of the last item returned by the iterator, starting from 1.
+    /// Zero if nothing has been read yet.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Number of bytes read so far, up to the end of the last line read.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+}
+
+impl<R, B, P> Iterator for BlockReader<R, B, P>
+where
+    R: BufRead,
+    P: BlockParser<B>,
+{
+    type Item = Result<B, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let line_offset = self.byte_offset;
+            self.buffer.clear();
+
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(read) => {
+                    self.line += 1;
+                    self.byte_offset += read as u64;
+
+                    let block = parse_line(&self.buffer, self.line, line_offset, &mut self.parser);
+                    if let Some(block) = block {
+                        return Some(block);
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => {
+                    self.done = true;
+                    self.line += 1;
+
+                    return Some(Err(ParseError {
+                        line: self.line,
+                        byte_offset: line_offset,
+                        kind: ParseErrorKind::Io(error.kind()),
+                    }));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over parsed blocks that knows the line of the last block.
+pub(crate) trait LineBlocks<B>: Iterator<Item = Result<B, ParseError>> {
+    /// Line of the last item returned by the iterator, starting from 1.
+    fn line(&self) -> usize;
+}
+
+impl<B, T: LineBlocks<B>> LineBlocks<B> for &mut T {
+    fn line(&self) -> usize {
+        (**self).line()
+    }
+}
+
+impl<R, B, P> LineBlocks<B> for BlockReader<R, B, P>
+where
+    R: BufRead,
+    P: BlockParser<B>,
+{
+    fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// Parse a single `line` starting at `line_offset`. Returns `None` if there is no block in it.
+pub(crate) fn parse_line<B>(
+    line: &[u8],
+    line_number: usize,
+    line_offset: u64,
+    parser: &mut impl BlockParser<B>,
+) -> Option<Result<B, ParseError>> {
+    let content = match line.iter().position(|byte| *byte == COMMENT) {
+        Some(comment_start) => &line[..comment_start],
+        None => line,
+    };
+
+    let trimmed = content.trim_ascii_start();
+    let value_offset = line_offset + (content.len() - trimmed.len()) as u64;
+    let trimmed = trimmed.trim_ascii_end();
+
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let error = |byte_offset, kind| ParseError {
+        line: line_number,
+        byte_offset,
+        kind,
+    };
+
+    let value = match str::from_utf8(trimmed) {
+        Ok(value) => value,
+        Err(utf8_error) => {
+            let byte_offset = value_offset + utf8_error.valid_up_to() as u64;
+            return Some(Err(error(byte_offset, ParseErrorKind::InvalidUtf8)));
+        }
+    };
+
+    Some(parser.parse_block(value).map_err(|reason| {
+        let kind = ParseErrorKind::InvalidBlock {
+            value: value.to_owned(),
+            reason: reason.to_string(),
+        };
+        error(value_offset, kind)
+    }))
+}
+
+/// Error of feeding a text input to a [Mine].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum IngestError<E> {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// Failure reported by the [Mine]. `line` holds the line of the rejected block,
+    /// or `None` if the input ended early.
+    #[error("{source} Line: {}.", line.map_or("end of input".to_owned(), |line| line.to_string()))]
+    Mine { line: Option<usize>, source: E },
+}
+
+/// Feed text input to a [Mine]. See [BlockReader] for the format of the input.
+/// Has a blanket implementation for every [Mine].
+///
+/// Blocks are parsed with [FromStr], or any [BlockParser] with the `_with` methods.
+/// Parse failures and validation failures are both reported as an [IngestError]
+/// carrying the line of the failure.
+pub trait Ingest<const VALIDATION_WINDOW_SIZE: usize, B>: Mine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block,
+{
+    /// Same as [Mine::try_extend] with the blocks read from `reader`.
+    fn try_ingest(&mut self, reader: impl BufRead) -> Result<(), IngestError<Self::Error>>
+    where
+        B: FromStr,
+        B::Err: Display,
+    {
+        self.try_ingest_with(reader, FromStrParser)
+    }
+
+    /// Same as [Ingest::try_ingest] with the blocks parsed by `parser`.
+    fn try_ingest_with(
+        &mut self,
+        reader: impl BufRead,
+        parser: impl BlockParser<B>,
+    ) -> Result<(), IngestError<Self::Error>> {
+        extend_from_blocks(self, &mut BlockReader::with_parser(reader, parser))
+    }
+
+    /// Same as [Mine::try_create_and_extend] with the blocks read from `reader`.
+    fn try_create_and_ingest(reader: impl BufRead) -> Result<(), IngestError<Self::Error>>
+    where
+        Self: Sized,
+        B: FromStr,
+        B::Err: Display,
+    {
+        Self::try_create_and_ingest_with(reader, FromStrParser)
+    }
+
+    /// Same as [Ingest::try_create_and_ingest] with the blocks parsed by `parser`, for example
+    /// a [NumberParser](crate::number::NumberParser).
+    fn try_create_and_ingest_with(
+        reader: impl BufRead,
+        parser: impl BlockParser<B>,
+    ) -> Result<(), IngestError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        create_and_extend_from_blocks::<VALIDATION_WINDOW_SIZE, B, Self>(BlockReader::with_parser(
+            reader, parser,
+        ))
+    }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B, M> Ingest<VALIDATION_WINDOW_SIZE, B> for M
+where
+    B: Block,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+}
+
+/// Same as [Ingest::try_create_and_ingest] with already parsed `blocks`.
+pub(crate) fn create_and_extend_from_blocks<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+    mut blocks: impl LineBlocks<B>,
+) -> Result<(), IngestError<M::Error>>
+where
+    B: Block,
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+{
+    let initialization_blocks = blocks
+        .by_ref()
+        .take(VALIDATION_WINDOW_SIZE)
+        .collect::<Result<Vec<B>, _>>()?;
+
+    let mut mine = M::try_new(initialization_blocks)
+        .map_err(|source| IngestError::Mine { line: None, source })?;
+
+    extend_from_blocks(&mut mine, &mut blocks)
+}
+
+/// Extend the `mine` with the remaining `blocks`, reporting failures with their line.
+fn extend_from_blocks<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+    mine: &mut M,
+    blocks: &mut impl LineBlocks<B>,
+) -> Result<(), IngestError<M::Error>>
+where
+    B: Block,
+    M: Mine<VALIDATION_WINDOW_SIZE, B> + ?Sized,
+{
+    while let Some(block) = blocks.next() {
+        mine.try_extend_one(block?)
+            .map_err(|source| IngestError::Mine {
+                line: Some(blocks.line()),
+                source,
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine, mine::MineError, number::NumberFormat, two_ptr_mine::TwoPtrMine,
+    };
+
+    fn parse(input: &str) -> Vec<Result<u32, ParseError>> {
+        BlockReader::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn format() {
+        let input = "1\n  2 \r\n\n# comment\n3 # trailing comment\r\n\t4";
+        assert_eq!(parse(input), [Ok(1), Ok(2), Ok(3), Ok(4)]);
+
+        assert_eq!(parse(""), []);
+        assert_eq!(parse("\n\r\n  \n#"), []);
+    }
+
+    #[test]
+    fn errors() {
+        let result = parse("1\r\n  x2\n3\n");
+        assert_eq!(
+            result[1],
+            Err(ParseError {
+                line: 2,
+                byte_offset: 5,
+                kind: ParseErrorKind::InvalidBlock {
+                    value: "x2".to_owned(),
+                    reason: "invalid digit found in string".to_owned()
+                }
+            })
+        );
+        assert_eq!(result[2], Ok(3), "Parsing continues after invalid values");
+
+        let result: Vec<Result<u32, _>> = BlockReader::new(&b"1\n 2\xff\n"[..]).collect();
+        assert_eq!(
+            result[1],
+            Err(ParseError {
+                line: 2,
+                byte_offset: 4,
+                kind: ParseErrorKind::InvalidUtf8
+            })
+        );
+
+        let result = parse("1\n-1\n");
+        assert!(matches!(
+            result[1],
+            Err(ParseError {
+                line: 2,
+                byte_offset: 2,
+                kind: ParseErrorKind::InvalidBlock { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn ingest() {
+        let input =
+            "# example\n35\n20\n15\n25\n47\n\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n";
+
+        let result = HashMine::<5, u32>::try_create_and_ingest(input.as_bytes());
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(17),
+                source: MineError::InvalidBlock(127, 15)
+            })
+        );
+
+        let result = TwoPtrMine::<5, u32>::try_create_and_ingest("35\n20\n#15\n".as_bytes());
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: None,
+                source: MineError::InvalidInitializationSequenceLen
+            })
+        );
+
+        let result = TwoPtrMine::<5, u32>::try_create_and_ingest(
+            "35\n20\n15\n25\n47\n\n40\n4O\n".as_bytes(),
+        );
+        assert!(matches!(
+            result,
+            Err(IngestError::Parse(ParseError {
+                line: 8,
+                byte_offset: 19,
+                ..
+            }))
+        ));
+
+        let mut mine = TwoPtrMine::<5, u32>::new([35, 20, 15, 25, 47]);
+        assert_eq!(mine.try_ingest("40\n62\n".as_bytes()), Ok(()));
+        assert_eq!(mine.total_blocks(), 7);
+
+        let parser = NumberFormat::any().parser();
+        assert_eq!(
+            mine.try_ingest_with("0x37\n".as_bytes(), parser.clone()),
+            Ok(())
+        );
+        let result =
+            HashMine::<2, u32>::try_create_and_ingest_with("1\n0x2\n3e0\n6\n".as_bytes(), parser);
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(4),
+                source: MineError::InvalidBlock(6, 4)
+            })
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::mine::{Block, Mine, MineError};
+
+use std::{borrow::Cow, cmp::Ordering, ops::Add};
+
+/// Decides which pairs of blocks in the validation window can justify a new block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SummandPolicy {
+    /// Summands are any two blocks at different positions in the window,
+    /// even if they have the same value. Used by every [Mine] in the crate.
+    #[default]
+    DistinctPositions,
+    /// Summands are two blocks with different values.
+    DistinctValues,
+}
+
+/// Wraps a [Mine] and validates new blocks with the given [SummandPolicy].
+///
+/// [SummandPolicy::DistinctValues] is checked on top of the wrapped mine. If the mine
+/// doesn't provide an [ordered window](Mine::ordered_validation_blocks) the window is sorted
+/// for every new block.
+#[derive(Clone, Debug)]
+pub struct PolicyMine<M> {
+    mine: M,
+    policy: SummandPolicy,
+}
+
+impl<M> PolicyMine<M> {
+    pub fn with_policy(mine: M, policy: SummandPolicy) -> Self {
+        Self { mine, policy }
+    }
+
+    pub fn policy(&self) -> SummandPolicy {
+        self.policy
+    }
+
+    pub fn into_inner(self) -> M {
+        self.mine
+    }
+
+    /// Check `new_block` against the rules of the policy that go beyond the wrapped mine.
+    fn check_policy<const VALIDATION_WINDOW_SIZE: usize, B>(
+        &self,
+        new_block: &B,
+    ) -> Result<(), M::Error>
+    where
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+        B: Block + Clone + Ord,
+        for<'a> &'a B: Add<&'a B, Output = B>,
+    {
+        if self.policy == SummandPolicy::DistinctPositions {
+            return Ok(());
+        }
+
+        let ordered_validation_blocks = match self.mine.ordered_validation_blocks() {
+            Some(blocks) => Cow::Borrowed(blocks),
+            None => {
+                let mut blocks: Vec<B> = self.mine.validation_blocks().cloned().collect();
+                blocks.sort_unstable();
+                Cow::Owned(blocks)
+            }
+        };
+
+        if has_distinct_values_pair(&ordered_validation_blocks, new_block) {
+            Ok(())
+        } else {
+            Err(MineError::<VALIDATION_WINDOW_SIZE, B>::InvalidBlock(
+                new_block.clone(),
+                self.mine.total_blocks() + 1,
+            )
+            .into())
+        }
+    }
+}
+
+/// Two pointer search for a pair of different values in the `ordered_blocks` summing up to `target`.
+pub(crate) fn has_distinct_values_pair<B>(ordered_blocks: &[B], target: &B) -> bool
+where
+    B: Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    let (mut i, mut j) = (0, ordered_blocks.len().saturating_sub(1));
+
+    while i < j {
+        let (min, max) = (&ordered_blocks[i], &ordered_blocks[j]);
+
+        match (min + max).cmp(target) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j -= 1,
+            // every block between min and max has the same value,
+            // so there is no other pair left to try
+            Ordering::Equal => return min != max,
+        }
+    }
+
+    false
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B, M> Mine<VALIDATION_WINDOW_SIZE, B> for PolicyMine<M>
+where
+    M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    B: Block + Clone + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    type Error = M::Error;
+
+    /// Recreate the wrapped mine with the default [SummandPolicy], see [Mine::resume].
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        Self::with_policy(
+            M::resume(validation_blocks, total_blocks),
+            SummandPolicy::default(),
+        )
+    }
+
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error> {
+        self.check_policy(&new_block)?;
+        self.mine.try_extend_one(new_block)
+    }
+
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, Self::Error> {
+        match self.policy {
+            SummandPolicy::DistinctPositions => self.mine.try_extend_one_justified(new_block),
+            SummandPolicy::DistinctValues => self.try_extend_one(new_block).map(|()| None),
+        }
+    }
+
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error> {
+        self.check_policy(new_block)?;
+        self.mine.peek(new_block)
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a,
+    {
+        self.mine.validation_blocks()
+    }
+
+    fn total_blocks(&self) -> u64 {
+        self.mine.total_blocks()
+    }
+
+    fn ordered_validation_blocks(&self) -> Option<&[B]> {
+        self.mine.ordered_validation_blocks()
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        self.mine.distinct_pair_sums()
+    }
+
+    /// Only known for [SummandPolicy::DistinctPositions], the pair found by the
+    /// wrapped mine may not satisfy other policies.
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)> {
+        match self.policy {
+            SummandPolicy::DistinctPositions => self.mine.justifying_pair(new_block),
+            SummandPolicy::DistinctValues => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{hash_mine::HashMine, two_ptr_mine::TwoPtrMine};
+
+    #[test]
+    fn distinct_values_pair() {
+        assert!(has_distinct_values_pair(&[1, 2, 3], &5));
+        assert!(has_distinct_values_pair(&[2, 2, 3], &5));
+        assert!(!has_distinct_values_pair(&[2, 2, 3], &4));
+        assert!(has_distinct_values_pair(&[1, 2, 2, 2, 3], &4));
+        assert!(!has_distinct_values_pair(&[2], &4));
+        assert!(!has_distinct_values_pair::<u32>(&[], &4));
+    }
+
+    #[test]
+    fn policies() {
+        let initial_blocks = [2, 2, 3, 5];
+
+        let mine = PolicyMine::<HashMine<4, u32>>::new(initial_blocks);
+        assert_eq!(mine.policy(), SummandPolicy::DistinctPositions);
+        assert_eq!(mine.peek(&4), Ok(()));
+
+        let mut mine = PolicyMine::with_policy(mine.into_inner(), SummandPolicy::DistinctValues);
+        assert_eq!(mine.try_extend_one(4), Err(MineError::InvalidBlock(4, 5)));
+        assert_eq!(mine.try_extend_one(5), Ok(()));
+        assert_eq!(mine.total_blocks(), 5);
+
+        let mut mine = PolicyMine::with_policy(
+            TwoPtrMine::<4, u32>::new(initial_blocks),
+            SummandPolicy::DistinctValues,
+        );
+        assert_eq!(mine.peek(&4), Err(MineError::InvalidBlock(4, 5)));
+        assert_eq!(mine.try_extend_one(7), Ok(()));
+        // window is now [2, 3, 5, 7]
+        assert_eq!(mine.try_extend_one(4), Err(MineError::InvalidBlock(4, 6)));
+        assert_eq!(mine.try_extend_one(10), Ok(()));
+    }
+}
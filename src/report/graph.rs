@@ -0,0 +1,346 @@
+use crate::{
+    certificate::justifying_offsets,
+    mine::{Block, Mine},
+};
+
+use super::jsonl::push_value;
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write as _},
+    io::{self, Write},
+    ops::Add,
+};
+
+/// Graph of the blocks justifying each other: every accepted block has an edge from
+/// each of the two blocks it is the sum of. Initialization blocks have no edges in.
+///
+/// Export with [JustificationGraph::write_dot] or [JustificationGraph::write_json], and
+/// narrow it down to the history of one block with [JustificationGraph::ancestry].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JustificationGraph<B> {
+    nodes: BTreeMap<u64, Node<B>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node<B> {
+    value: B,
+    /// Numbers of the two blocks justifying this one, the older one first.
+    /// `None` for initialization blocks.
+    pair: Option<(u64, u64)>,
+}
+
+impl<B: Block + Clone> JustificationGraph<B> {
+    /// Start a graph with the validation window of a new `mine`, its initialization blocks.
+    pub fn new<const VALIDATION_WINDOW_SIZE: usize, M>(mine: &M) -> Self
+    where
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let first_block_number = mine.total_blocks() + 1 - VALIDATION_WINDOW_SIZE as u64;
+        let nodes = (first_block_number..)
+            .zip(mine.validation_blocks())
+            .map(|(block_number, value)| {
+                let node = Node {
+                    value: value.clone(),
+                    pair: None,
+                };
+                (block_number, node)
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    /// Validate `new_block` with [Mine::try_extend_one] and record the pair justifying it
+    /// if it's accepted. The pair is found as for a [certificate](crate::certificate).
+    pub fn try_extend_one<const VALIDATION_WINDOW_SIZE: usize, M>(
+        &mut self,
+        mine: &mut M,
+        new_block: B,
+    ) -> Result<(), M::Error>
+    where
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+        for<'a> &'a B: Add<&'a B, Output = B>,
+    {
+        let block_number = mine.total_blocks() + 1;
+        let offsets = justifying_offsets(mine, &new_block);
+        let value = new_block.clone();
+
+        mine.try_extend_one(new_block)?;
+
+        let (newer, older) =
+            offsets.expect("an accepted block is the sum of two blocks in the window");
+        let node = Node {
+            value,
+            pair: Some((block_number - older as u64, block_number - newer as u64)),
+        };
+        self.nodes.insert(block_number, node);
+        Ok(())
+    }
+
+    /// Number of blocks in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Numbers of the two blocks justifying block number `block_number`, the older one first.
+    /// `None` for initialization blocks and blocks not in the graph.
+    pub fn pair(&self, block_number: u64) -> Option<(u64, u64)> {
+        self.nodes.get(&block_number)?.pair
+    }
+
+    /// Value of block number `block_number`, `None` if it isn't in the graph.
+    pub fn value(&self, block_number: u64) -> Option<&B> {
+        self.nodes.get(&block_number).map(|node| &node.value)
+    }
+
+    /// Numbers of the initialization blocks in the graph.
+    pub fn initialization_blocks(&self) -> impl Iterator<Item = u64> + '_ {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.pair.is_none())
+            .map(|(block_number, _)| *block_number)
+    }
+
+    /// Subgraph of block number `block_number` and every block it was justified with,
+    /// directly or not. `None` if the block isn't in the graph. Ancestors missing from
+    /// the graph, like in a [JustificationGraph::dependents] subgraph, are left out.
+    pub fn ancestry(&self, block_number: u64) -> Option<Self> {
+        self.nodes.get(&block_number)?;
+        let mut nodes = BTreeMap::new();
+        let mut pending = vec![block_number];
+
+        while let Some(block_number) = pending.pop() {
+            if nodes.contains_key(&block_number) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&block_number) else {
+                continue;
+            };
+            if let Some((first, second)) = node.pair {
+                pending.extend([first, second]);
+            }
+            nodes.insert(block_number, node.clone());
+        }
+
+        Some(Self { nodes })
+    }
+
+    /// Subgraph of block number `block_number` and every block justified with it,
+    /// directly or not. `None` if the block isn't in the graph.
+    pub fn dependents(&self, block_number: u64) -> Option<Self> {
+        let node = self.nodes.get(&block_number)?;
+        let mut nodes = BTreeMap::from([(block_number, node.clone())]);
+
+        // blocks are only justified by older blocks
+        for (number, node) in self.nodes.range(block_number + 1..) {
+            if let Some((first, second)) = node.pair {
+                if nodes.contains_key(&first) || nodes.contains_key(&second) {
+                    nodes.insert(*number, node.clone());
+                }
+            }
+        }
+
+        Some(Self { nodes })
+    }
+
+    /// Numbers of the blocks in the graph, in ascending order.
+    pub fn block_numbers(&self) -> impl Iterator<Item = u64> + '_ {
+        self.nodes.keys().copied()
+    }
+
+    /// Write the graph in the Graphviz [DOT](https://graphviz.org/doc/info/lang.html)
+    /// language. Nodes are named by block number, initialization blocks are boxes:
+    /// ```text
+    /// digraph justification {
+    ///   3 [label="3: 15", shape=box];
+    ///   6 [label="6: 40"];
+    ///   3 -> 6;
+    ///   4 -> 6;
+    /// }
+    /// ```
+    pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()>
+    where
+        B: Display,
+    {
+        writeln!(writer, "digraph justification {{")?;
+        for (block_number, node) in &self.nodes {
+            let label = format!("{block_number}: {}", node.value)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            match node.pair {
+                Some(_) => writeln!(writer, "  {block_number} [label=\"{label}\"];")?,
+                None => writeln!(writer, "  {block_number} [label=\"{label}\", shape=box];")?,
+            }
+        }
+        for (block_number, node) in &self.nodes {
+            if let Some((first, second)) = node.pair {
+                writeln!(writer, "  {first} -> {block_number};")?;
+                writeln!(writer, "  {second} -> {block_number};")?;
+            }
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+
+    /// Write the graph as JSON, one block per line. `pair` is missing for initialization
+    /// blocks, values are written as in [JsonLinesWriter](super::jsonl::JsonLinesWriter):
+    /// ```json
+    /// {"blocks":[
+    /// {"block":3,"value":15},
+    /// {"block":6,"value":40,"pair":[3,4]}
+    /// ]}
+    /// ```
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()>
+    where
+        B: Display,
+    {
+        let mut record = String::new();
+
+        writeln!(writer, "{{\"blocks\":[")?;
+        for (i, (block_number, node)) in self.nodes.iter().enumerate() {
+            record.clear();
+            write!(record, "{{\"block\":{block_number},\"value\":")
+                .expect("writing to a String can't fail");
+            push_value(&mut record, &node.value);
+            if let Some((first, second)) = node.pair {
+                write!(record, ",\"pair\":[{first},{second}]")
+                    .expect("writing to a String can't fail");
+            }
+            record.push('}');
+            if i + 1 < self.nodes.len() {
+                record.push(',');
+            }
+            writeln!(writer, "{record}")?;
+        }
+        writeln!(writer, "]}}")?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine, mine::MineError, test_data::RECOVERING_EXAMPLE,
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn record<M: Mine<5, u64>>() -> JustificationGraph<u64> {
+        let mut mine = M::new(RECOVERING_EXAMPLE[..5].try_into().unwrap());
+        let mut graph = JustificationGraph::new(&mine);
+        for block in &RECOVERING_EXAMPLE[5..] {
+            let result = graph.try_extend_one(&mut mine, *block);
+            assert_eq!(result.is_err(), *block == 127);
+        }
+        graph
+    }
+
+    #[test]
+    fn pairs() {
+        let graph = record::<HashMine<5, u64>>();
+        assert_eq!(graph.len(), 17);
+        assert_eq!(graph.pair(3), None);
+        assert_eq!(graph.pair(6), Some((3, 4)));
+        // 182 + 219
+        assert_eq!(graph.pair(17), Some((14, 15)));
+        assert_eq!(graph.pair(18), None);
+        assert_eq!(graph, record::<TwoPtrMine<5, u64>>());
+
+        let mut mine = HashMine::<3, u64>::new([1, 2, 3]);
+        let mut graph = JustificationGraph::new(&mine);
+        assert_eq!(
+            graph.try_extend_one(&mut mine, 10),
+            Err(MineError::InvalidBlock(10, 4))
+        );
+        assert_eq!(graph.len(), 3);
+    }
+
+    #[test]
+    fn ancestry() {
+        let graph = record::<HashMine<5, u64>>();
+        assert_eq!(graph.ancestry(18), None);
+
+        // 40 = 15 + 25
+        let ancestry = graph.ancestry(6).unwrap();
+        assert_eq!(ancestry.len(), 3);
+        assert_eq!(ancestry.initialization_blocks().collect::<Vec<_>>(), [3, 4]);
+
+        // 35 and 20 never justify anything
+        let ancestry = graph.ancestry(17).unwrap();
+        assert_eq!(
+            ancestry.initialization_blocks().collect::<Vec<_>>(),
+            [3, 4, 5]
+        );
+        assert!(ancestry.len() < graph.len());
+        assert_eq!(ancestry.ancestry(17), Some(ancestry.clone()));
+
+        // block 17 is justified by blocks 14 and 15, the dependents of 15 leave 14 out
+        let dependents = graph.dependents(15).unwrap();
+        let ancestry = dependents.ancestry(17).unwrap();
+        assert_eq!(ancestry.block_numbers().collect::<Vec<_>>(), [15, 17]);
+    }
+
+    #[test]
+    fn dependents() {
+        let graph = record::<HashMine<5, u64>>();
+        assert_eq!(graph.dependents(18), None);
+        assert_eq!(graph.value(6), Some(&40));
+
+        // 35 and 20 never justify anything
+        let dependents = graph.dependents(1).unwrap();
+        assert_eq!(dependents.block_numbers().collect::<Vec<_>>(), [1]);
+
+        let dependents = graph.dependents(15).unwrap();
+        assert_eq!(dependents.block_numbers().collect::<Vec<_>>(), [15, 17]);
+        for block_number in 1..=17 {
+            let depends = graph.ancestry(block_number).unwrap().value(15).is_some();
+            assert_eq!(dependents.value(block_number).is_some(), depends);
+        }
+    }
+
+    #[test]
+    fn export() {
+        let ancestry = record::<HashMine<5, u64>>().ancestry(6).unwrap();
+
+        let mut dot = Vec::new();
+        ancestry.write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            r#"digraph justification {
+  3 [label="3: 15", shape=box];
+  4 [label="4: 25", shape=box];
+  6 [label="6: 40"];
+  3 -> 6;
+  4 -> 6;
+}
+"#
+        );
+
+        let mut json = Vec::new();
+        ancestry.write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"{"blocks":[
+{"block":3,"value":15},
+{"block":4,"value":25},
+{"block":6,"value":40,"pair":[3,4]}
+]}
+"#
+        );
+
+        let empty = JustificationGraph::<u64> {
+            nodes: BTreeMap::new(),
+        };
+        let mut json = Vec::new();
+        empty.write_json(&mut json).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), "{\"blocks\":[\n]}\n");
+    }
+}
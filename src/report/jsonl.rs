@@ -0,0 +1,258 @@
+use crate::mine::{try_extend_recorded, Block, Mine};
+
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, Write},
+};
+
+/// Which blocks get a record.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    #[default]
+    AllBlocks,
+    RejectedOnly,
+}
+
+/// Writes validation results as [JSON Lines](https://jsonlines.org/).
+///
+/// Every validated block gets a record:
+/// ```json
+/// {"type":"block","block":6,"value":40,"status":"accepted","pair":[15,25]}
+/// {"type":"block","block":15,"value":127,"status":"rejected"}
+/// ```
+/// `pair` holds the two blocks justifying an accepted block, if the [Mine] can tell
+/// which they are. See [Mine::justifying_pair].
+///
+/// [JsonLinesWriter::finish] writes the summary record:
+/// ```json
+/// {"type":"summary","accepted":13,"rejected":1,"total_blocks":19}
+/// ```
+/// Block values that are safe integers in JavaScript, up to 2^53 - 1 in magnitude, are
+/// written as numbers. Larger values are written as strings so they don't lose precision.
+#[derive(Debug)]
+pub struct JsonLinesWriter<W> {
+    writer: W,
+    verbosity: Verbosity,
+    accepted: u64,
+    rejected: u64,
+    /// Holds the record being written.
+    record: String,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W, verbosity: Verbosity) -> Self {
+        Self {
+            writer,
+            verbosity,
+            accepted: 0,
+            rejected: 0,
+            record: String::new(),
+        }
+    }
+
+    /// Validate `new_block` and write its record, see [try_extend_recorded].
+    pub fn try_extend_one<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &mut M,
+        new_block: B,
+    ) -> io::Result<Result<(), M::Error>>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        try_extend_recorded(mine, new_block, |mine, block, validation| {
+            self.start_block_record(mine.total_blocks() + 1, block);
+            match validation {
+                Ok(()) => self.end_accepted(mine.justifying_pair(block)),
+                Err(_) => self.end_rejected(),
+            }
+        })
+    }
+
+    /// Write the summary record and return the writer.
+    /// `total_blocks` is the number of blocks in the mine, see [Mine::total_blocks].
+    pub fn finish(mut self, total_blocks: u64) -> io::Result<W> {
+        self.record.clear();
+        write!(
+            self.record,
+            "{{\"type\":\"summary\",\"accepted\":{},\"rejected\":{},\"total_blocks\":{total_blocks}",
+            self.accepted, self.rejected
+        )
+        .expect("writing to a String can't fail");
+        self.write_record()?;
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn start_block_record<B: Display>(&mut self, block_number: u64, value: &B) {
+        self.record.clear();
+        write!(
+            self.record,
+            "{{\"type\":\"block\",\"block\":{block_number},\"value\":"
+        )
+        .expect("writing to a String can't fail");
+        push_value(&mut self.record, value);
+    }
+
+    fn end_accepted<B: Display>(&mut self, pair: Option<(&B, &B)>) -> io::Result<()> {
+        self.accepted += 1;
+        if self.verbosity == Verbosity::RejectedOnly {
+            return Ok(());
+        }
+
+        self.record.push_str(",\"status\":\"accepted\"");
+        if let Some((first, second)) = pair {
+            self.record.push_str(",\"pair\":[");
+            push_value(&mut self.record, first);
+            self.record.push(',');
+            push_value(&mut self.record, second);
+            self.record.push(']');
+        }
+        self.write_record()
+    }
+
+    fn end_rejected(&mut self) -> io::Result<()> {
+        self.rejected += 1;
+
+        self.record.push_str(",\"status\":\"rejected\"");
+        self.write_record()
+    }
+
+    fn write_record(&mut self) -> io::Result<()> {
+        self.record.push_str("}\n");
+        self.writer.write_all(self.record.as_bytes())
+    }
+}
+
+/// Push a block `value` to the JSON `record`. Integers in the safe range of JavaScript
+/// numbers are pushed as numbers, anything else as a string.
+pub(super) fn push_value(record: &mut String, value: &impl Display) {
+    /// `Number.MAX_SAFE_INTEGER`, larger integers may not survive a round trip
+    /// through a JavaScript number.
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+    let start = record.len();
+    write!(record, "{value}").expect("writing to a String can't fail");
+
+    let text = &record[start..];
+    if text
+        .parse::<i64>()
+        .is_ok_and(|value| value.unsigned_abs() <= MAX_SAFE_INTEGER)
+    {
+        return;
+    }
+
+    let text = record.split_off(start);
+    record.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => record.push_str("\\\""),
+            '\\' => record.push_str("\\\\"),
+            c if c.is_control() => {
+                write!(record, "\\u{:04x}", c as u32).expect("writing to a String can't fail")
+            }
+            c => record.push(c),
+        }
+    }
+    record.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine, mine::MineError, test_data::example, two_ptr_mine::TwoPtrMine,
+    };
+
+    fn report<M: Mine<5, u128>>(verbosity: Verbosity) -> String {
+        let mut mine = M::new(example::<u128>()[..5].try_into().unwrap());
+        let mut writer = JsonLinesWriter::new(Vec::new(), verbosity);
+
+        for block in example::<u128>()[5..8]
+            .iter()
+            .chain(&example::<u128>()[14..15])
+        {
+            writer.try_extend_one(&mut mine, *block).unwrap().ok();
+        }
+
+        let output = writer.finish(mine.total_blocks()).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            report::<TwoPtrMine<5, u128>>(Verbosity::AllBlocks),
+            r#"{"type":"block","block":6,"value":40,"status":"accepted","pair":[15,25]}
+{"type":"block","block":7,"value":62,"status":"accepted","pair":[15,47]}
+{"type":"block","block":8,"value":55,"status":"accepted","pair":[15,40]}
+{"type":"block","block":9,"value":127,"status":"rejected"}
+{"type":"summary","accepted":3,"rejected":1,"total_blocks":8}
+"#
+        );
+
+        assert_eq!(
+            report::<HashMine<5, u128>>(Verbosity::AllBlocks),
+            r#"{"type":"block","block":6,"value":40,"status":"accepted"}
+{"type":"block","block":7,"value":62,"status":"accepted"}
+{"type":"block","block":8,"value":55,"status":"accepted"}
+{"type":"block","block":9,"value":127,"status":"rejected"}
+{"type":"summary","accepted":3,"rejected":1,"total_blocks":8}
+"#,
+            "HashMine can't tell which pair justified a block"
+        );
+
+        assert_eq!(
+            report::<HashMine<5, u128>>(Verbosity::RejectedOnly),
+            report::<TwoPtrMine<5, u128>>(Verbosity::RejectedOnly)
+        );
+        assert_eq!(
+            report::<HashMine<5, u128>>(Verbosity::RejectedOnly),
+            r#"{"type":"block","block":9,"value":127,"status":"rejected"}
+{"type":"summary","accepted":3,"rejected":1,"total_blocks":8}
+"#
+        );
+    }
+
+    #[test]
+    fn large_values() {
+        let large = u64::MAX as u128 + 1;
+        let mut mine = TwoPtrMine::<2, u128>::new([large, 1]);
+        let mut writer = JsonLinesWriter::new(Vec::new(), Verbosity::AllBlocks);
+
+        assert_eq!(writer.try_extend_one(&mut mine, large + 1).unwrap(), Ok(()));
+        assert_eq!(
+            writer.try_extend_one(&mut mine, 7).unwrap(),
+            Err(MineError::InvalidBlock(7, 4))
+        );
+
+        let output = String::from_utf8(writer.finish(mine.total_blocks()).unwrap()).unwrap();
+        assert_eq!(
+            output,
+            r#"{"type":"block","block":3,"value":"18446744073709551617","status":"accepted","pair":[1,"18446744073709551616"]}
+{"type":"block","block":4,"value":7,"status":"rejected"}
+{"type":"summary","accepted":1,"rejected":1,"total_blocks":3}
+"#
+        );
+
+        let mut record = String::new();
+        push_value(&mut record, &-5i128);
+        push_value(&mut record, &"a \"b\"\n");
+        assert_eq!(record, r#"-5"a \"b\"\u000a""#);
+
+        // beyond the safe integers of JavaScript
+        let mut record = String::new();
+        for value in [(1i64 << 53) - 1, 1 << 53, -(1 << 53) + 1, -(1 << 53)] {
+            push_value(&mut record, &value);
+            record.push(' ');
+        }
+        assert_eq!(
+            record,
+            r#"9007199254740991 "9007199254740992" -9007199254740991 "-9007199254740992" "#
+        );
+    }
+}
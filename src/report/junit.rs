@@ -0,0 +1,388 @@
+use crate::{
+    mine::{Block, Mine, MineError},
+    parse::{BlockReader, ParseError},
+};
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+/// Validation outcome of a single input, reported as a JUnit `<testsuite>`.
+///
+/// Every invalid block is a `<failure>`, parse errors and inputs too short to fill the
+/// validation window are `<error>`s. Valid blocks are reported by a single passing test case,
+/// `valid blocks`, with their number in its `<system-out>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestSuite {
+    name: String,
+    /// Number of valid blocks, initialization blocks excluded.
+    valid_blocks: usize,
+    failures: Vec<Failure>,
+    errors: Vec<Error>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Failure {
+    block_number: u64,
+    value: String,
+    line: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Error {
+    /// Name of the test case reporting the error.
+    name: String,
+    kind: &'static str,
+    message: String,
+}
+
+impl TestSuite {
+    /// Create an empty suite. `name` is usually the path of the input.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            valid_blocks: 0,
+            failures: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Validate the blocks read from `reader` with a new `M`, see [BlockReader] for the format.
+    ///
+    /// Unlike [Mine::try_create_and_extend] validation doesn't stop at the first invalid block.
+    /// Invalid blocks and blocks that fail to parse are skipped, so every one of them is reported
+    /// and block numbers count valid blocks only.
+    pub fn validate<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        name: impl Into<String>,
+        reader: impl BufRead,
+    ) -> Self
+    where
+        B: Block + FromStr + Display,
+        B::Err: Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B, Error = MineError<VALIDATION_WINDOW_SIZE, B>>,
+    {
+        let mut suite = Self::new(name);
+        let mut blocks = BlockReader::new(reader);
+
+        let mut initialization_blocks = Vec::with_capacity(VALIDATION_WINDOW_SIZE);
+        while initialization_blocks.len() < VALIDATION_WINDOW_SIZE {
+            match blocks.next() {
+                Some(Ok(block)) => initialization_blocks.push(block),
+                Some(Err(error)) => suite.add_parse_error(&error),
+                None => break,
+            }
+        }
+
+        let mut mine = match M::try_new(initialization_blocks) {
+            Ok(mine) => mine,
+            Err(error) => {
+                suite.add_mine_error(&error, None);
+                return suite;
+            }
+        };
+
+        while let Some(block) = blocks.next() {
+            match block.map(|block| mine.try_extend_one(block)) {
+                Ok(Ok(())) => suite.add_valid_blocks(1),
+                Ok(Err(error)) => suite.add_mine_error(&error, Some(blocks.line())),
+                Err(error) => suite.add_parse_error(&error),
+            }
+        }
+
+        suite
+    }
+
+    /// Count `count` more valid blocks.
+    pub fn add_valid_blocks(&mut self, count: usize) {
+        self.valid_blocks += count;
+    }
+
+    /// Report the `error` returned by a [Mine], e.g. by [Mine::try_create_and_extend].
+    /// `line` is the line of the input holding the invalid block, if known.
+    pub fn add_mine_error<const VALIDATION_WINDOW_SIZE: usize, B>(
+        &mut self,
+        error: &MineError<VALIDATION_WINDOW_SIZE, B>,
+        line: Option<usize>,
+    ) where
+        B: Block + Display,
+    {
+        match error {
+            MineError::InvalidBlock(value, block_number) => self.failures.push(Failure {
+                block_number: *block_number,
+                value: value.to_string(),
+                line,
+            }),
+            MineError::InvalidInitializationSequenceLen => self.errors.push(Error {
+                name: "initialization".to_owned(),
+                kind: "InvalidInitializationSequenceLen",
+                message: format!(
+                    "Input has less than {VALIDATION_WINDOW_SIZE} blocks, \
+                    not enough to fill the validation window."
+                ),
+            }),
+        }
+    }
+
+    /// Report a failure to parse the input.
+    pub fn add_parse_error(&mut self, error: &ParseError) {
+        self.errors.push(Error {
+            name: format!("line {}", error.line),
+            kind: "ParseError",
+            message: error.to_string(),
+        });
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of test cases in the suite.
+    pub fn tests(&self) -> usize {
+        usize::from(self.valid_blocks > 0) + self.failures.len() + self.errors.len()
+    }
+
+    /// Number of invalid blocks.
+    pub fn failures(&self) -> usize {
+        self.failures.len()
+    }
+
+    /// Number of parse and initialization errors.
+    pub fn errors(&self) -> usize {
+        self.errors.len()
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let name = escape(&self.name);
+        writeln!(
+            writer,
+            r#"  <testsuite name="{name}" tests="{}" failures="{}" errors="{}">"#,
+            self.tests(),
+            self.failures(),
+            self.errors()
+        )?;
+
+        if self.valid_blocks > 0 {
+            // the name stays the same between runs, so CI can track the test case
+            writeln!(
+                writer,
+                r#"    <testcase name="valid blocks" classname="{name}">"#
+            )?;
+            writeln!(
+                writer,
+                "      <system-out>Valid blocks: {}</system-out>",
+                self.valid_blocks
+            )?;
+            writeln!(writer, "    </testcase>")?;
+        }
+
+        for failure in &self.failures {
+            let value = escape(&failure.value);
+            // rejected blocks don't move the block number, their lines tell them apart
+            let case = match failure.line {
+                Some(line) => format!("line {line}"),
+                None => format!("block {}", failure.block_number),
+            };
+            writeln!(writer, r#"    <testcase name="{case}" classname="{name}">"#)?;
+            write!(
+                writer,
+                r#"      <failure type="InvalidBlock" message="Invalid block {}: {value}">"#,
+                failure.block_number
+            )?;
+            write!(
+                writer,
+                "Block {} with value {value} is not the sum of two blocks in the validation window.",
+                failure.block_number
+            )?;
+            if let Some(line) = failure.line {
+                write!(writer, " Line: {line}.")?;
+            }
+            writeln!(writer, "</failure>")?;
+            writeln!(writer, "    </testcase>")?;
+        }
+
+        for error in &self.errors {
+            writeln!(
+                writer,
+                r#"    <testcase name="{}" classname="{name}">"#,
+                escape(&error.name)
+            )?;
+            writeln!(
+                writer,
+                r#"      <error type="{}" message="{}"/>"#,
+                error.kind,
+                escape(&error.message)
+            )?;
+            writeln!(writer, "    </testcase>")?;
+        }
+
+        writeln!(writer, "  </testsuite>")
+    }
+}
+
+/// JUnit XML report of validation runs over one or more inputs.
+/// Each input is a [TestSuite].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JUnitReport {
+    name: String,
+    suites: Vec<TestSuite>,
+}
+
+impl JUnitReport {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            suites: Vec::new(),
+        }
+    }
+
+    pub fn add_suite(&mut self, suite: TestSuite) {
+        self.suites.push(suite);
+    }
+
+    pub fn suites(&self) -> &[TestSuite] {
+        &self.suites
+    }
+
+    /// `true` if no suite has failures or errors.
+    pub fn is_success(&self) -> bool {
+        self.suites
+            .iter()
+            .all(|suite| suite.failures() == 0 && suite.errors() == 0)
+    }
+
+    /// Write the report as JUnit XML.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let total = |count: fn(&TestSuite) -> usize| self.suites.iter().map(count).sum::<usize>();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<testsuites name="{}" tests="{}" failures="{}" errors="{}">"#,
+            escape(&self.name),
+            total(TestSuite::tests),
+            total(TestSuite::failures),
+            total(TestSuite::errors)
+        )?;
+        for suite in &self.suites {
+            suite.write(&mut writer)?;
+        }
+        writeln!(writer, "</testsuites>")?;
+
+        writer.flush()
+    }
+}
+
+/// Escape `text` for use in XML attributes and text. Characters XML 1.0
+/// can't represent are replaced with U+FFFD.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{hash_mine::HashMine, two_ptr_mine::TwoPtrMine};
+
+    fn xml(report: &JUnitReport) -> String {
+        let mut output = Vec::new();
+        report.write_to(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn suites() {
+        let example = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n127\n";
+        let mut report = JUnitReport::new("tmmt");
+        report.add_suite(TestSuite::validate::<5, u64, HashMine<5, u64>>(
+            "example.txt",
+            example.as_bytes(),
+        ));
+        report.add_suite(TestSuite::validate::<5, u64, TwoPtrMine<5, u64>>(
+            "a&b.txt",
+            "1\n2\n3\nx\n4\n5\n7\n".as_bytes(),
+        ));
+        report.add_suite(TestSuite::validate::<5, u64, TwoPtrMine<5, u64>>(
+            "short.txt",
+            "1\n2\n".as_bytes(),
+        ));
+
+        assert!(!report.is_success());
+        assert_eq!(
+            xml(&report),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="tmmt" tests="6" failures="2" errors="2">
+  <testsuite name="example.txt" tests="3" failures="2" errors="0">
+    <testcase name="valid blocks" classname="example.txt">
+      <system-out>Valid blocks: 9</system-out>
+    </testcase>
+    <testcase name="line 15" classname="example.txt">
+      <failure type="InvalidBlock" message="Invalid block 15: 127">Block 15 with value 127 is not the sum of two blocks in the validation window. Line: 15.</failure>
+    </testcase>
+    <testcase name="line 16" classname="example.txt">
+      <failure type="InvalidBlock" message="Invalid block 15: 127">Block 15 with value 127 is not the sum of two blocks in the validation window. Line: 16.</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="a&amp;b.txt" tests="2" failures="0" errors="1">
+    <testcase name="valid blocks" classname="a&amp;b.txt">
+      <system-out>Valid blocks: 1</system-out>
+    </testcase>
+    <testcase name="line 4" classname="a&amp;b.txt">
+      <error type="ParseError" message="Failed to parse line 4 at byte offset 6: Invalid block value &quot;x&quot;: invalid digit found in string."/>
+    </testcase>
+  </testsuite>
+  <testsuite name="short.txt" tests="1" failures="0" errors="1">
+    <testcase name="initialization" classname="short.txt">
+      <error type="InvalidInitializationSequenceLen" message="Input has less than 5 blocks, not enough to fill the validation window."/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn create_and_extend_outcome() {
+        let blocks = [35u64, 20, 15, 25, 47, 40, 62, 55, 65, 200];
+
+        let mut suite = TestSuite::new("example");
+        if let Err(error) = TwoPtrMine::<5, u64>::try_create_and_extend(blocks) {
+            suite.add_mine_error(&error, None);
+        }
+        assert_eq!((suite.tests(), suite.failures(), suite.errors()), (1, 1, 0));
+
+        let mut report = JUnitReport::new("tmmt");
+        report.add_suite(TestSuite::new("empty"));
+        assert!(report.is_success());
+        report.add_suite(suite);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            escape("<a href='x'>\"&\"</a>"),
+            "&lt;a href=&apos;x&apos;&gt;&quot;&amp;&quot;&lt;/a&gt;"
+        );
+        assert_eq!(escape("a\nb\u{0}"), "a&#10;b\u{fffd}");
+    }
+}
@@ -0,0 +1,552 @@
+use crate::mine::{try_extend_recorded, Block, Mine};
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use rusqlite::{params, Connection};
+use thiserror::Error;
+
+/// Window snapshots taken between two accepted blocks, unless set with
+/// [SqliteSink::with_snapshot_interval].
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 10_000;
+
+/// Blocks recorded in one transaction, unless set with [SqliteSink::with_batch_size].
+pub const DEFAULT_BATCH_SIZE: u64 = 1_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS blocks (
+    block_number INTEGER PRIMARY KEY,
+    value TEXT NOT NULL,
+    recorded_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE TABLE IF NOT EXISTS rejections (
+    id INTEGER PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    error TEXT NOT NULL,
+    recorded_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    block_number INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (block_number, position)
+);
+";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum SqliteError {
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Database holds blocks of a validation window of {window_size} blocks, expected {expected}.")]
+    WindowSizeMismatch { window_size: u64, expected: u64 },
+    #[error("Database doesn't hold the history up to block {0}.")]
+    MissingHistory(u64),
+    #[error("Invalid value {value:?} of block {block_number} in the database: {reason}.")]
+    InvalidValue {
+        block_number: u64,
+        value: String,
+        reason: String,
+    },
+    #[error("Block {0} in the database is rejected by the mine.")]
+    InvalidHistory(u64),
+}
+
+impl From<rusqlite::Error> for SqliteError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Database(error.to_string())
+    }
+}
+
+/// Records the history of a [Mine] in an SQLite database.
+///
+/// The schema, with block values stored as text so blocks of any width fit:
+///
+/// | table        | columns                                             |
+/// |--------------|-----------------------------------------------------|
+/// | `settings`   | `key`, `value`: the `window_size` of the mine       |
+/// | `blocks`     | `block_number`, `value`, `recorded_at`              |
+/// | `rejections` | `id`, `block_number`, `value`, `error`, `recorded_at` |
+/// | `snapshots`  | `block_number`, `position`, `value`                 |
+///
+/// - `blocks` holds every accepted block, initialization blocks included.
+/// - `rejections` holds the rejected blocks, with the number they would have had and the
+///   [Mine::Error] message.
+/// - `snapshots` holds the validation window, in arrival `position` from 0, after
+///   `block_number` blocks were added to the mine.
+/// - `recorded_at` is an ISO 8601 UTC timestamp.
+///
+/// For example accepted blocks per day, and the window of 100 blocks that block 1000
+/// was validated with:
+/// ```sql
+/// SELECT date(recorded_at), count(*) FROM blocks GROUP BY 1;
+/// SELECT value FROM blocks WHERE block_number BETWEEN 900 AND 999 ORDER BY block_number;
+/// ```
+///
+/// Blocks are recorded in transactions of [SqliteSink::with_batch_size] blocks, committed
+/// when full, at every snapshot, on [SqliteSink::commit] and when the sink is dropped.
+/// Until then the database can be behind the mine, a crash loses the pending blocks:
+/// [rebuild](SqliteSink::rebuild) the mine from the database to resume.
+#[derive(Debug)]
+pub struct SqliteSink {
+    connection: Connection,
+    snapshot_interval: u64,
+    /// Blocks added to the mine at the last snapshot.
+    snapshot_blocks: u64,
+    batch_size: u64,
+    /// Rows written in the open transaction.
+    pending: u64,
+}
+
+impl SqliteSink {
+    /// Open or create the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Use an open `connection`, creating the tables if they are missing.
+    pub fn with_connection(connection: Connection) -> Result<Self, SqliteError> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            snapshot_blocks: 0,
+            batch_size: DEFAULT_BATCH_SIZE,
+            pending: 0,
+        })
+    }
+
+    /// Commit every `batch_size` recorded blocks, at least 1. A larger batch saves
+    /// syncing the database for every block, a smaller one loses fewer blocks in a crash.
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Take a snapshot every `snapshot_interval` accepted blocks, at least 1.
+    pub fn with_snapshot_interval(mut self, snapshot_interval: u64) -> Self {
+        self.snapshot_interval = snapshot_interval.max(1);
+        self
+    }
+
+    /// The database, to run queries on.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Start recording the `mine`: its validation window is recorded as accepted blocks,
+    /// and a snapshot is taken. Fails if the database holds a mine with a different
+    /// validation window size.
+    pub fn attach<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &M,
+    ) -> Result<(), SqliteError>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let expected = VALIDATION_WINDOW_SIZE as u64;
+        self.connection.execute(
+            "INSERT OR IGNORE INTO settings (key, value) VALUES ('window_size', ?1)",
+            [expected.to_string()],
+        )?;
+        let window_size: String = self.connection.query_row(
+            "SELECT value FROM settings WHERE key = 'window_size'",
+            [],
+            |row| row.get(0),
+        )?;
+        if window_size != expected.to_string() {
+            return Err(SqliteError::WindowSizeMismatch {
+                window_size: window_size.parse().unwrap_or_default(),
+                expected,
+            });
+        }
+
+        self.begin()?;
+        let first_block_number = mine.total_blocks() + 1 - expected;
+        for (block_number, block) in (first_block_number..).zip(mine.validation_blocks()) {
+            self.connection
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO blocks (block_number, value) VALUES (?1, ?2)",
+                )?
+                .execute(params![block_number, block.to_string()])?;
+        }
+
+        self.snapshot(mine)
+    }
+
+    /// Validate `new_block` and record it, see [try_extend_recorded].
+    pub fn try_extend_one<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &mut M,
+        new_block: B,
+    ) -> Result<Result<(), M::Error>, SqliteError>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+        M::Error: Display,
+    {
+        let result = try_extend_recorded(mine, new_block, |mine, block, validation| {
+            let block_number = mine.total_blocks() + 1;
+            match validation {
+                Ok(()) => self.record_accepted(block_number, block),
+                Err(error) => self.record_rejected(block_number, block, error),
+            }
+        })?;
+
+        if result.is_ok() && mine.total_blocks() - self.snapshot_blocks >= self.snapshot_interval {
+            self.snapshot(mine)?;
+        }
+        Ok(result)
+    }
+
+    /// Record an accepted block, committed with the batch.
+    pub fn record_accepted(
+        &mut self,
+        block_number: u64,
+        value: &impl Display,
+    ) -> Result<(), SqliteError> {
+        self.begin()?;
+        self.connection
+            .prepare_cached("INSERT INTO blocks (block_number, value) VALUES (?1, ?2)")?
+            .execute(params![block_number, value.to_string()])?;
+        self.row_written()
+    }
+
+    /// Record a rejected block with the `error` it was rejected with, committed with the batch.
+    pub fn record_rejected(
+        &mut self,
+        block_number: u64,
+        value: &impl Display,
+        error: &impl Display,
+    ) -> Result<(), SqliteError> {
+        self.begin()?;
+        self.connection
+            .prepare_cached(
+                "INSERT INTO rejections (block_number, value, error) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![block_number, value.to_string(), error.to_string()])?;
+        self.row_written()
+    }
+
+    /// Commit the blocks recorded since the last commit.
+    pub fn commit(&mut self) -> Result<(), SqliteError> {
+        if !self.connection.is_autocommit() {
+            self.connection.execute_batch("COMMIT")?;
+        }
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Open a transaction for the next batch, unless one is open.
+    fn begin(&mut self) -> Result<(), SqliteError> {
+        if self.connection.is_autocommit() {
+            self.connection.execute_batch("BEGIN")?;
+        }
+        Ok(())
+    }
+
+    fn row_written(&mut self) -> Result<(), SqliteError> {
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Record the validation window of the `mine` and commit.
+    /// Taken automatically, see [SqliteSink::with_snapshot_interval].
+    pub fn snapshot<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &mut self,
+        mine: &M,
+    ) -> Result<(), SqliteError>
+    where
+        B: Block + Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let block_number = mine.total_blocks();
+
+        self.begin()?;
+        for (position, block) in mine.validation_blocks().enumerate() {
+            self.connection
+                .prepare_cached(
+                    "INSERT OR REPLACE INTO snapshots (block_number, position, value) \
+                    VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![block_number, position, block.to_string()])?;
+        }
+        self.commit()?;
+
+        self.snapshot_blocks = block_number;
+        Ok(())
+    }
+
+    /// Rebuild the mine as it was after `block_number` blocks were added to it.
+    /// Starts from the latest snapshot at or before the block, and validates the
+    /// accepted blocks recorded after it again.
+    pub fn rebuild<const VALIDATION_WINDOW_SIZE: usize, B, M>(
+        &self,
+        block_number: u64,
+    ) -> Result<M, SqliteError>
+    where
+        B: Block + FromStr,
+        B::Err: Display,
+        M: Mine<VALIDATION_WINDOW_SIZE, B>,
+    {
+        let snapshot_blocks: Option<u64> = self.connection.query_row(
+            "SELECT max(block_number) FROM snapshots WHERE block_number <= ?1",
+            [block_number],
+            |row| row.get(0),
+        )?;
+        let snapshot_blocks = snapshot_blocks.ok_or(SqliteError::MissingHistory(block_number))?;
+
+        let window = self.query_blocks(
+            "SELECT block_number, value FROM snapshots WHERE block_number = ?1 ORDER BY position",
+            [snapshot_blocks],
+        )?;
+        let window: [B; VALIDATION_WINDOW_SIZE] = window
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect::<Vec<B>>()
+            .try_into()
+            .map_err(|_| SqliteError::MissingHistory(snapshot_blocks))?;
+
+        let mut mine = M::resume(window, snapshot_blocks);
+        let blocks = self.query_blocks(
+            "SELECT block_number, value FROM blocks \
+            WHERE block_number > ?1 AND block_number <= ?2 ORDER BY block_number",
+            [snapshot_blocks, block_number],
+        )?;
+        for (number, block) in blocks {
+            if number != mine.total_blocks() + 1 {
+                return Err(SqliteError::MissingHistory(mine.total_blocks() + 1));
+            }
+            mine.try_extend_one(block)
+                .map_err(|_| SqliteError::InvalidHistory(number))?;
+        }
+
+        if mine.total_blocks() != block_number {
+            return Err(SqliteError::MissingHistory(block_number));
+        }
+        Ok(mine)
+    }
+
+    /// Block numbers and parsed values of the rows of a `block_number, value` query.
+    fn query_blocks<B>(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<(u64, B)>, SqliteError>
+    where
+        B: FromStr,
+        B::Err: Display,
+    {
+        let mut statement = self.connection.prepare(query)?;
+        let rows = statement.query_map(params, |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        rows.map(|row| {
+            let (block_number, value) = row?;
+            let block = value
+                .parse()
+                .map_err(|reason: B::Err| SqliteError::InvalidValue {
+                    block_number,
+                    value: value.clone(),
+                    reason: reason.to_string(),
+                })?;
+            Ok((block_number, block))
+        })
+        .collect()
+    }
+}
+
+impl Drop for SqliteSink {
+    /// Commit the pending blocks, ignoring errors. Call [SqliteSink::commit] to handle them.
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::MineError,
+        test_data::{FIRST_INVALID_BLOCK_NUMBER, RECOVERING_EXAMPLE},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    fn record_example() -> SqliteSink {
+        let mut sink = SqliteSink::with_connection(Connection::open_in_memory().unwrap())
+            .unwrap()
+            .with_snapshot_interval(4);
+        let mut mine = HashMine::<5, u64>::try_new(&RECOVERING_EXAMPLE[..5]).unwrap();
+        sink.attach(&mine).unwrap();
+
+        for block in &RECOVERING_EXAMPLE[5..] {
+            sink.try_extend_one(&mut mine, *block).unwrap().ok();
+        }
+        sink
+    }
+
+    fn query_numbers(sink: &SqliteSink, query: &str) -> Vec<u64> {
+        let mut statement = sink.connection().prepare(query).unwrap();
+        let rows = statement.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn record() {
+        let sink = record_example();
+
+        assert_eq!(
+            query_numbers(&sink, "SELECT count(*) FROM blocks"),
+            [17],
+            "Initialization blocks are recorded"
+        );
+        assert_eq!(
+            query_numbers(&sink, "SELECT DISTINCT block_number FROM snapshots"),
+            [5, 9, 13, 17]
+        );
+
+        let rejection: (u64, String, String) = sink
+            .connection()
+            .query_row(
+                "SELECT block_number, value, error FROM rejections",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            rejection,
+            (
+                15,
+                "127".to_owned(),
+                MineError::<5, u64>::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER).to_string()
+            )
+        );
+
+        // window block 15 was validated with
+        let window: Vec<String> = {
+            let mut statement = sink
+                .connection()
+                .prepare("SELECT value FROM blocks WHERE block_number BETWEEN 10 AND 14")
+                .unwrap();
+            let rows = statement.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(window, ["95", "102", "117", "150", "182"]);
+    }
+
+    #[test]
+    fn rebuild() {
+        let sink = record_example();
+
+        let accepted: Vec<u64> = RECOVERING_EXAMPLE
+            .into_iter()
+            .filter(|block| *block != 127)
+            .collect();
+        for block_number in 5..=17 {
+            let mine: TwoPtrMine<5, u64> = sink.rebuild(block_number).unwrap();
+            assert_eq!(mine.total_blocks(), block_number);
+
+            let end = block_number as usize;
+            assert!(mine.validation_blocks().eq(&accepted[end - 5..end]));
+        }
+
+        let mut mine: HashMine<5, u64> = sink.rebuild(14).unwrap();
+        assert_eq!(
+            mine.try_extend_one(127),
+            Err(MineError::InvalidBlock(127, FIRST_INVALID_BLOCK_NUMBER))
+        );
+
+        assert_eq!(
+            sink.rebuild::<5, u64, TwoPtrMine<5, u64>>(4).err(),
+            Some(SqliteError::MissingHistory(4))
+        );
+        assert_eq!(
+            sink.rebuild::<5, u64, TwoPtrMine<5, u64>>(18).err(),
+            Some(SqliteError::MissingHistory(18))
+        );
+    }
+
+    #[test]
+    fn batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.sqlite");
+        let mut sink = SqliteSink::open(&path).unwrap().with_batch_size(3);
+        let mut mine = TwoPtrMine::<5, u64>::try_new(&RECOVERING_EXAMPLE[..5]).unwrap();
+        sink.attach(&mine).unwrap();
+
+        let reader = SqliteSink::open(&path).unwrap();
+        let committed = || query_numbers(&reader, "SELECT count(*) FROM blocks");
+        for block in &RECOVERING_EXAMPLE[5..7] {
+            assert_eq!(sink.try_extend_one(&mut mine, *block), Ok(Ok(())));
+        }
+        assert_eq!(committed(), [5]);
+        assert_eq!(
+            sink.try_extend_one(&mut mine, RECOVERING_EXAMPLE[7]),
+            Ok(Ok(()))
+        );
+        assert_eq!(committed(), [8]);
+        assert_eq!(
+            sink.try_extend_one(&mut mine, RECOVERING_EXAMPLE[8]),
+            Ok(Ok(()))
+        );
+        sink.commit().unwrap();
+        assert_eq!(committed(), [9]);
+
+        // a block that can't be recorded isn't added to the mine
+        sink.connection()
+            .execute_batch(
+                "CREATE TRIGGER full BEFORE INSERT ON blocks \
+                BEGIN SELECT RAISE(ABORT, 'full'); END",
+            )
+            .unwrap();
+        assert!(sink
+            .try_extend_one(&mut mine, RECOVERING_EXAMPLE[9])
+            .is_err());
+        assert_eq!(mine.total_blocks(), 9);
+    }
+
+    #[test]
+    fn reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.sqlite");
+
+        let mut sink = SqliteSink::open(&path).unwrap();
+        let mut mine = TwoPtrMine::<5, u64>::try_new(&RECOVERING_EXAMPLE[..5]).unwrap();
+        sink.attach(&mine).unwrap();
+        for block in &RECOVERING_EXAMPLE[5..10] {
+            assert_eq!(sink.try_extend_one(&mut mine, *block), Ok(Ok(())));
+        }
+        drop(sink);
+
+        let mut sink = SqliteSink::open(&path).unwrap();
+        let mut mine: TwoPtrMine<5, u64> = sink.rebuild(10).unwrap();
+        sink.attach(&mine).unwrap();
+        assert_eq!(sink.try_extend_one(&mut mine, 102), Ok(Ok(())));
+        assert_eq!(
+            query_numbers(&sink, "SELECT max(block_number) FROM blocks"),
+            [11]
+        );
+
+        let other = HashMine::<4, u64>::try_new(&RECOVERING_EXAMPLE[..4]).unwrap();
+        assert_eq!(
+            sink.attach(&other),
+            Err(SqliteError::WindowSizeMismatch {
+                window_size: 5,
+                expected: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Example stream shared by the tests, also in `resources/test_input.txt`.
+
+/// Example of the challenge, with a validation window of 5.
+pub(crate) const EXAMPLE: [u64; 20] = [
+    35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+];
+
+/// [EXAMPLE] with blocks of type `B`.
+pub(crate) fn example<B: From<u64>>() -> [B; 20] {
+    EXAMPLE.map(B::from)
+}
+
+/// Number of 127, the first invalid block of [EXAMPLE]. Every block before it is valid.
+pub(crate) const FIRST_INVALID_BLOCK_NUMBER: u64 = 15;
+
+/// Numbers of the blocks of [EXAMPLE] a mine rejects: 127, 277, 309 and 576.
+/// Rejected blocks aren't added to the mine, so the last three share a number.
+pub(crate) const INVALID_BLOCK_NUMBERS: [u64; 4] = [15, 17, 17, 17];
+
+/// [EXAMPLE] up to its first invalid block, then blocks valid once it's rejected:
+/// 219 = 102 + 117, 332 = 150 + 182 and 401 = 182 + 219.
+pub(crate) const RECOVERING_EXAMPLE: [u64; 18] = [
+    35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 332, 401,
+];
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        mine::{Mine, MineError},
+    };
+
+    #[test]
+    fn test_input() {
+        let test_file_name = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test_input.txt");
+        let blocks: Vec<u64> = std::fs::read_to_string(test_file_name)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        assert_eq!(blocks, EXAMPLE);
+    }
+
+    #[test]
+    fn invalid_blocks() {
+        let rejected = |blocks: &[u64]| {
+            let mut mine = HashMine::<5, u64>::try_new(&blocks[..5]).unwrap();
+            blocks[5..]
+                .iter()
+                .filter_map(|block| mine.try_extend_one(*block).err())
+                .map(|error| match error {
+                    MineError::InvalidBlock(_, block_number) => block_number,
+                    error => panic!("unexpected error {error}"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rejected(&EXAMPLE), INVALID_BLOCK_NUMBERS);
+        assert_eq!(rejected(&RECOVERING_EXAMPLE), [FIRST_INVALID_BLOCK_NUMBER]);
+    }
+}
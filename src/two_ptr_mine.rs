@@ -1,69 +1,57 @@
-use crate::mine::{Block, Mine};
+use crate::mine::{Block, Mine, MineError, RUNTIME_WINDOW_SIZE};
 
 use std::{collections::VecDeque, ops::Add};
 
 /// Concrete implementation of [Mine]
 #[derive(Clone, Debug)]
-pub struct TwoPtrMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Copy + Ord> {
+pub struct TwoPtrMine<const VALIDATION_WINDOW_SIZE: usize, B: Block + Clone + Ord> {
     /// Holds [VALIDATION_WINDOW_SIZE] blocks used for validation.
-    validation_blocks: VecDeque<B>,
+    pub(crate) validation_blocks: VecDeque<B>,
     /// Holds all the possible two element sums from the [validation_blocks](Self::validation_blocks).
     /// Used for quick validation of new blocks.
     ordered_validation_blocks: Vec<B>,
     /// Tracks how many blocks have been validated
-    total_blocks: usize,
+    pub(crate) total_blocks: u64,
 }
 
-impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
-    for TwoPtrMine<VALIDATION_WINDOW_SIZE, B>
+impl<const VALIDATION_WINDOW_SIZE: usize, B> TwoPtrMine<VALIDATION_WINDOW_SIZE, B>
 where
-    B: Block + Copy + Ord,
-    for<'a> &'a B: Add<&'a B, Output = B>,
-    for<'a> B: Add<&'a B, Output = B>,
+    B: Block + Clone + Ord,
 {
-    fn new(mut initialization_blocks: [B; VALIDATION_WINDOW_SIZE]) -> Self {
-        let validation_blocks = initialization_blocks;
-        initialization_blocks.sort_unstable();
+    /// Create a mine from a full window of `validation_blocks`, given in arrival order,
+    /// after `total_blocks` blocks have been added to the mine. Any number of blocks makes
+    /// a full window for a mine of [RUNTIME_WINDOW_SIZE].
+    /// No validation is performed on the window.
+    pub(crate) fn from_window(validation_blocks: VecDeque<B>, total_blocks: u64) -> Self {
+        let window_size = validation_blocks.len();
+        debug_assert!(
+            VALIDATION_WINDOW_SIZE == RUNTIME_WINDOW_SIZE || window_size == VALIDATION_WINDOW_SIZE
+        );
+        assert!(
+            total_blocks >= window_size as u64,
+            "A mine holds at least {window_size} blocks, not {total_blocks}."
+        );
+
+        let mut ordered_validation_blocks = Vec::from(validation_blocks.clone());
+        ordered_validation_blocks.sort_unstable();
 
         Self {
-            validation_blocks: VecDeque::from(validation_blocks),
-            ordered_validation_blocks: Vec::from(initialization_blocks),
-            total_blocks: validation_blocks.len(),
+            validation_blocks,
+            ordered_validation_blocks,
+            total_blocks,
         }
     }
 
-    fn try_extend_one(
-        &mut self,
-        new_block: B,
-    ) -> Result<(), crate::mine::MineError<VALIDATION_WINDOW_SIZE, B>> {
-        // CHECK NEW BLOCK VALIDITY
-
-        let mut min_to_max = self.ordered_validation_blocks.iter().enumerate();
-        let mut max_to_min = self.ordered_validation_blocks.iter().enumerate().rev();
-
-        let mut min_item = min_to_max.next();
-        let mut max_item = max_to_min.next();
-
-        while let (Some((i, min)), Some((j, max))) = (min_item, max_item) {
-            // all possible (min, max) pairs exhausted
-            if i == j {
-                return Err(crate::mine::MineError::InvalidBlock(
-                    new_block,
-                    self.total_blocks + 1,
-                ));
-            }
-            match (min + max).cmp(&new_block) {
-                // min element can't be a part of the solution pair
-                std::cmp::Ordering::Less => min_item = min_to_max.next(),
-                // found solution pair
-                std::cmp::Ordering::Equal => break,
-                // max element can't be a part of the solution pair
-                std::cmp::Ordering::Greater => max_item = max_to_min.next(),
-            }
-
-            // TODO: we can search for the old block in this loop as an optimization attempt
-        }
+    /// Find two blocks in the validation window that sum up to `new_block`.
+    fn find_pair(&self, new_block: &B) -> Option<(&B, &B)>
+    where
+        for<'a> &'a B: Add<&'a B, Output = B>,
+    {
+        find_pair(&self.ordered_validation_blocks, new_block)
+    }
 
+    /// Add a valid `new_block` to the window, evicting the oldest block.
+    fn push(&mut self, new_block: B) {
         // NEW BLOCK IS VALID
         // now we can safely remove/insert items to validation blocks
 
@@ -71,7 +59,6 @@ where
             .validation_blocks
             .pop_front()
             .expect("validation_blocks have a minimum size VALIDATION_WINDOW_SIZE");
-        self.validation_blocks.push_back(new_block);
 
         // TODO:
         // - try mapping validation blocks to ordered validation blocks when you perform sort
@@ -86,29 +73,119 @@ where
             .ordered_validation_blocks
             .binary_search(&new_block)
             .unwrap_or_else(|i| i);
+        // the only clone on the hot path, it's a plain copy for `Copy` blocks
         self.ordered_validation_blocks
-            .insert(new_block_idx, new_block);
+            .insert(new_block_idx, new_block.clone());
+        self.validation_blocks.push_back(new_block);
 
         self.total_blocks += 1;
+    }
+}
+
+/// Two pointer search for two blocks in the `ordered_blocks` that sum up to `new_block`.
+pub(crate) fn find_pair<'a, B>(ordered_blocks: &'a [B], new_block: &B) -> Option<(&'a B, &'a B)>
+where
+    B: Ord,
+    for<'b> &'b B: Add<&'b B, Output = B>,
+{
+    let mut min_to_max = ordered_blocks.iter().enumerate();
+    let mut max_to_min = ordered_blocks.iter().enumerate().rev();
+
+    let mut min_item = min_to_max.next();
+    let mut max_item = max_to_min.next();
+
+    while let (Some((i, min)), Some((j, max))) = (min_item, max_item) {
+        // all possible (min, max) pairs exhausted
+        if i == j {
+            return None;
+        }
+        match (min + max).cmp(new_block) {
+            // min element can't be a part of the solution pair
+            std::cmp::Ordering::Less => min_item = min_to_max.next(),
+            // found solution pair
+            std::cmp::Ordering::Equal => return Some((min, max)),
+            // max element can't be a part of the solution pair
+            std::cmp::Ordering::Greater => max_item = max_to_min.next(),
+        }
+
+        // TODO: we can search for the old block in this loop as an optimization attempt
+    }
+
+    None
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B> Mine<VALIDATION_WINDOW_SIZE, B>
+    for TwoPtrMine<VALIDATION_WINDOW_SIZE, B>
+where
+    B: Block + Clone + Ord,
+    for<'a> &'a B: Add<&'a B, Output = B>,
+{
+    type Error = MineError<VALIDATION_WINDOW_SIZE, B>;
+
+    fn resume(validation_blocks: [B; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        Self::from_window(VecDeque::from(validation_blocks), total_blocks)
+    }
+
+    fn try_extend_one(&mut self, new_block: B) -> Result<(), Self::Error> {
+        if self.find_pair(&new_block).is_none() {
+            return Err(MineError::InvalidBlock(new_block, self.total_blocks + 1));
+        }
 
+        self.push(new_block);
         Ok(())
     }
+
+    fn try_extend_one_justified(&mut self, new_block: B) -> Result<Option<(B, B)>, Self::Error> {
+        let Some((first, second)) = self.find_pair(&new_block) else {
+            return Err(MineError::InvalidBlock(new_block, self.total_blocks + 1));
+        };
+        let pair = (first.clone(), second.clone());
+
+        self.push(new_block);
+        Ok(Some(pair))
+    }
+
+    fn peek(&self, new_block: &B) -> Result<(), Self::Error> {
+        match self.find_pair(new_block) {
+            Some(_) => Ok(()),
+            None => Err(MineError::InvalidBlock(
+                new_block.clone(),
+                self.total_blocks + 1,
+            )),
+        }
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a B>
+    where
+        B: 'a,
+    {
+        self.validation_blocks.iter()
+    }
+
+    fn total_blocks(&self) -> u64 {
+        self.total_blocks
+    }
+
+    fn ordered_validation_blocks(&self) -> Option<&[B]> {
+        Some(&self.ordered_validation_blocks)
+    }
+
+    fn justifying_pair(&self, new_block: &B) -> Option<(&B, &B)> {
+        self.find_pair(new_block)
+    }
 }
 
 // TODO: macro for tests
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
-    use std::{
-        array,
-        fs::File,
-        io::{BufRead, BufReader},
-    };
+    use std::{array, fs::File, io::BufReader};
 
     use super::*;
 
     use crate::mine::Mine as MineTrait;
     use crate::mine::MineError;
+    use crate::parse::{Ingest, IngestError};
 
     // max size of values in the test file
     type Block = u128;
@@ -191,6 +268,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn accessors() {
+        let mut mine = Mine::new([4, 4, 2, 2]);
+        assert_eq!(mine.total_blocks(), 4);
+        assert_eq!(mine.ordered_validation_blocks(), Some(&[2, 2, 4, 4][..]));
+        assert_eq!(mine.distinct_pair_sums(), None);
+
+        assert_eq!(mine.try_extend_one(8), Ok(()));
+        assert_eq!(mine.total_blocks(), 5);
+        assert_eq!(
+            mine.validation_blocks().copied().collect::<Vec<_>>(),
+            [4, 2, 2, 8]
+        );
+        assert_eq!(mine.ordered_validation_blocks(), Some(&[2, 2, 4, 8][..]));
+
+        assert_eq!(mine.justifying_pair(&10), Some((&2, &8)));
+        assert_eq!(mine.justifying_pair(&4), Some((&2, &2)));
+        assert_eq!(mine.justifying_pair(&16), None);
+    }
+
     #[test]
     fn smoke2() {
         let initial_blocks: [Block; 100] = array::from_fn(|i| i as Block + 1);
@@ -232,16 +329,36 @@ mod tests {
         let test_file = File::open(test_file_name).unwrap();
         let test_file = BufReader::new(test_file);
 
-        let blocks = test_file.lines().map(|block_value| {
-            block_value
-                .expect("test file must have only valid UTF-8 strings")
-                .trim()
-                .parse::<u128>()
-                .expect("test file must have only valid u128 values")
-        });
+        let result = Mine::<100, u128>::try_create_and_ingest(test_file);
+
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(315),
+                source: MineError::InvalidBlock(14, 315)
+            })
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_bigint_file() {
+        use crate::mine::BigBlock;
+
+        let test_file_name = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/bigint_input.txt");
+        let test_file = File::open(test_file_name).unwrap();
+        let test_file = BufReader::new(test_file);
 
-        let result = Mine::<100, u128>::try_create_and_extend(blocks);
+        let result = Mine::<5, BigBlock>::try_create_and_ingest(test_file);
 
-        assert_eq!(result, Err(MineError::InvalidBlock(14, 315)));
+        // the example scaled by 2^200
+        let scale = BigBlock::from(1u8) << 200;
+        assert_eq!(
+            result,
+            Err(IngestError::Mine {
+                line: Some(15),
+                source: MineError::InvalidBlock(127u8 * scale, 15)
+            })
+        );
     }
 }
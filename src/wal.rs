@@ -0,0 +1,697 @@
+use crate::{
+    binary::{self, Crc32, FixedWidthBlock},
+    mine::{Block, Mine, MineError},
+};
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+/// Name of the log file in the directory of a [WalMine].
+pub const LOG_FILE: &str = "blocks.wal";
+/// Name of the snapshot file in the directory of a [WalMine].
+pub const SNAPSHOT_FILE: &str = "window.snapshot";
+/// Blocks accepted between two snapshots, unless set with [WalMine::with_snapshot_interval].
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 100_000;
+
+const LOG_MAGIC: [u8; 4] = *b"TMWL";
+const SNAPSHOT_MAGIC: [u8; 4] = *b"TMWS";
+const VERSION: u8 = 1;
+/// Size of the header shared by the log and the snapshot files in bytes.
+const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WalError<const VALIDATION_WINDOW_SIZE: usize, B: Block> {
+    #[error(transparent)]
+    Mine(#[from] MineError<VALIDATION_WINDOW_SIZE, B>),
+    #[error("Invalid write-ahead log file {file}: {reason}.")]
+    Invalid {
+        file: &'static str,
+        reason: &'static str,
+    },
+    #[error("Failed to read or write the write-ahead log: {0}.")]
+    Io(io::ErrorKind),
+    #[error("The write-ahead log failed to write earlier, reopen it to recover.")]
+    Poisoned,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B: Block> From<io::Error>
+    for WalError<VALIDATION_WINDOW_SIZE, B>
+{
+    fn from(error: io::Error) -> Self {
+        Self::Io(error.kind())
+    }
+}
+
+/// What [WalMine::open] recovered from the log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Blocks in the log after the snapshot, validated again.
+    pub replayed_blocks: u64,
+    /// Bytes of torn or truncated records cut from the end of the log.
+    pub discarded_bytes: u64,
+}
+
+/// [Mine] persisted to a directory, so validation survives a crash.
+///
+/// Every accepted block is appended to a checksummed log before it's acknowledged, and
+/// the validation window is written to a snapshot every few blocks, emptying the log.
+/// [WalMine::open] loads the snapshot and validates the blocks in the log again.
+///
+/// Files in the directory, with numbers in little endian:
+/// - [LOG_FILE]: header, then one record per block: block number (8 bytes), block,
+///   CRC-32 of the two (4 bytes).
+/// - [SNAPSHOT_FILE]: header, number of blocks added to the mine (8 bytes), the validation
+///   window in arrival order, CRC-32 of everything before it (4 bytes). Replaced atomically.
+///
+/// Both headers are 16 bytes: magic `TMWL` or `TMWS`, format version, block width in bytes,
+/// flags (`0b01` for signed blocks), a zero byte and the validation window size (8 bytes).
+///
+/// A block is appended to the log before it's added to the mine. After a failed write or
+/// snapshot the `WalMine` refuses every call with [WalError::Poisoned], reopen it with
+/// [WalMine::open] to continue from what reached the disk.
+///
+/// Works with mines of [FixedWidthBlock]s reporting plain [MineError]s, the blocks are
+/// logged in their binary form and the log is replayed with [Mine::try_extend_one].
+/// `WalMine` doesn't implement [Mine] itself, its methods can fail with I/O errors.
+#[derive(Debug)]
+pub struct WalMine<const VALIDATION_WINDOW_SIZE: usize, B, M> {
+    mine: M,
+    dir: PathBuf,
+    log: File,
+    /// Blocks added to the mine at the last snapshot.
+    snapshot_blocks: u64,
+    snapshot_interval: u64,
+    sync: bool,
+    recovery: Recovery,
+    /// Set by a failed write, the log may not match the mine anymore.
+    poisoned: bool,
+    _block: PhantomData<fn() -> B>,
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, B, M> WalMine<VALIDATION_WINDOW_SIZE, B, M>
+where
+    B: FixedWidthBlock,
+    M: Mine<VALIDATION_WINDOW_SIZE, B, Error = MineError<VALIDATION_WINDOW_SIZE, B>>,
+{
+    /// Create a mine with given `initialization_blocks`, persisted to the `dir`ectory.
+    /// The directory is created if missing. Fails if it already holds a log, unless it's
+    /// an empty log without a snapshot, left by a crash in an earlier `create`.
+    /// No validation is performed on the initialization blocks.
+    pub fn create(
+        dir: impl AsRef<Path>,
+        initialization_blocks: [B; VALIDATION_WINDOW_SIZE],
+    ) -> Result<Self, WalError<VALIDATION_WINDOW_SIZE, B>> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)?;
+
+        let log_path = dir.join(LOG_FILE);
+        let unfinished = !dir.join(SNAPSHOT_FILE).try_exists()?
+            && fs::metadata(&log_path).is_ok_and(|log| log.len() <= HEADER_LEN as u64);
+        if unfinished {
+            fs::remove_file(&log_path)?;
+        }
+
+        if log_path.try_exists()? {
+            return Err(WalError::Io(io::ErrorKind::AlreadyExists));
+        }
+
+        // the snapshot goes first, a log without one can't be opened
+        let mine = M::new(initialization_blocks);
+        write_snapshot_file(&dir, mine.total_blocks(), mine.validation_blocks().copied())?;
+
+        let mut log = OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(&log_path)?;
+        log.write_all(&encode_header::<B>(LOG_MAGIC, VALIDATION_WINDOW_SIZE))?;
+        log.sync_all()?;
+        sync_dir(&dir)?;
+
+        let mut wal = Self::from_parts(mine, dir, log);
+        wal.snapshot_blocks = wal.total_blocks();
+        Ok(wal)
+    }
+
+    /// Recover the mine persisted to the `dir`ectory. Records torn by a crash are cut
+    /// from the end of the log, see [WalMine::recovery].
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, WalError<VALIDATION_WINDOW_SIZE, B>> {
+        let dir = dir.as_ref().to_owned();
+
+        let snapshot = fs::read(dir.join(SNAPSHOT_FILE))?;
+        let (snapshot_blocks, window) = decode_snapshot::<VALIDATION_WINDOW_SIZE, B>(&snapshot)?;
+
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        check_header::<VALIDATION_WINDOW_SIZE, B>(&bytes, LOG_MAGIC, LOG_FILE)?;
+
+        let mut wal = Self::from_parts(M::resume(window, snapshot_blocks), dir, log);
+        wal.snapshot_blocks = snapshot_blocks;
+
+        let mut valid_len = HEADER_LEN;
+        for record in bytes[HEADER_LEN..].chunks(record_len::<B>()) {
+            // a torn write can only be the last record
+            let Some((block_number, block)) = decode_record::<B>(record) else {
+                break;
+            };
+
+            let next_block_number = wal.total_blocks() + 1;
+            if block_number > next_block_number {
+                return Err(WalError::Invalid {
+                    file: LOG_FILE,
+                    reason: "blocks are missing",
+                });
+            }
+            // records written before the last snapshot are already in it
+            if block_number == next_block_number {
+                wal.mine
+                    .try_extend_one(block)
+                    .map_err(|_| WalError::Invalid {
+                        file: LOG_FILE,
+                        reason: "the mine rejects a logged block",
+                    })?;
+                wal.recovery.replayed_blocks += 1;
+            }
+            valid_len += record.len();
+        }
+
+        if valid_len < bytes.len() {
+            wal.recovery.discarded_bytes = (bytes.len() - valid_len) as u64;
+            wal.log.set_len(valid_len as u64)?;
+            wal.log.sync_all()?;
+        }
+
+        Ok(wal)
+    }
+
+    fn from_parts(mine: M, dir: PathBuf, log: File) -> Self {
+        Self {
+            mine,
+            dir,
+            log,
+            snapshot_blocks: 0,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            sync: false,
+            recovery: Recovery::default(),
+            poisoned: false,
+            _block: PhantomData,
+        }
+    }
+
+    /// Take a snapshot every `snapshot_interval` accepted blocks, at least 1.
+    pub fn with_snapshot_interval(self, snapshot_interval: u64) -> Self {
+        Self {
+            snapshot_interval: snapshot_interval.max(1),
+            ..self
+        }
+    }
+
+    /// Flush every record to the disk before acknowledging the block, not only to the
+    /// operating system. Survives power loss as well, at the cost of a sync per block.
+    pub fn with_sync(self, sync: bool) -> Self {
+        Self { sync, ..self }
+    }
+
+    /// Validate the `new_block`, append it to the log if it's valid, then add it to the mine.
+    ///
+    /// If writing fails the block isn't added to the mine, but may be in the log.
+    /// The `WalMine` is [poisoned](WalError::Poisoned), reopening it recovers the logged blocks.
+    pub fn try_extend_one(
+        &mut self,
+        new_block: B,
+    ) -> Result<(), WalError<VALIDATION_WINDOW_SIZE, B>> {
+        self.check_poisoned()?;
+        self.mine.peek(&new_block)?;
+
+        let block_number = self.total_blocks() + 1;
+        let record = encode_record(block_number, new_block);
+        let written = self.log.write_all(&record).and_then(|()| {
+            if self.sync {
+                self.log.sync_data()?;
+            }
+            Ok(())
+        });
+        self.poison_on_error(written)?;
+
+        self.mine.try_extend_one(new_block)?;
+
+        if block_number - self.snapshot_blocks >= self.snapshot_interval {
+            self.snapshot()?;
+        }
+        Ok(())
+    }
+
+    /// Same as [Mine::try_extend] with the blocks logged by [WalMine::try_extend_one].
+    pub fn try_extend(
+        &mut self,
+        blocks: impl IntoIterator<Item = B>,
+    ) -> Result<(), WalError<VALIDATION_WINDOW_SIZE, B>> {
+        for block in blocks {
+            self.try_extend_one(block)?
+        }
+        Ok(())
+    }
+
+    /// Replace the snapshot with the current validation window and empty the log.
+    /// Taken automatically, see [WalMine::with_snapshot_interval].
+    pub fn snapshot(&mut self) -> Result<(), WalError<VALIDATION_WINDOW_SIZE, B>> {
+        self.check_poisoned()?;
+        let written = self.write_snapshot();
+        self.poison_on_error(written)
+    }
+
+    fn write_snapshot(&mut self) -> io::Result<()> {
+        let total_blocks = self.total_blocks();
+        write_snapshot_file(
+            &self.dir,
+            total_blocks,
+            self.mine.validation_blocks().copied(),
+        )?;
+
+        // every logged block is in the snapshot now
+        self.log.set_len(HEADER_LEN as u64)?;
+        self.log.sync_all()?;
+        self.snapshot_blocks = total_blocks;
+        Ok(())
+    }
+
+    fn check_poisoned(&self) -> Result<(), WalError<VALIDATION_WINDOW_SIZE, B>> {
+        match self.poisoned {
+            true => Err(WalError::Poisoned),
+            false => Ok(()),
+        }
+    }
+
+    fn poison_on_error<T>(
+        &mut self,
+        result: io::Result<T>,
+    ) -> Result<T, WalError<VALIDATION_WINDOW_SIZE, B>> {
+        result.map_err(|error| {
+            self.poisoned = true;
+            error.into()
+        })
+    }
+
+    pub fn mine(&self) -> &M {
+        &self.mine
+    }
+
+    /// Number of blocks added to the mine so far, across restarts.
+    pub fn total_blocks(&self) -> u64 {
+        self.mine.total_blocks()
+    }
+
+    /// What was recovered when the mine was opened. Empty for a created mine.
+    pub fn recovery(&self) -> Recovery {
+        self.recovery
+    }
+}
+
+/// Replace the snapshot in the `dir`ectory atomically.
+fn write_snapshot_file<B: FixedWidthBlock>(
+    dir: &Path,
+    total_blocks: u64,
+    window: impl ExactSizeIterator<Item = B>,
+) -> io::Result<()> {
+    let path = dir.join(SNAPSHOT_FILE);
+    let temp_path = path.with_extension("tmp");
+    let mut file = File::create(&temp_path)?;
+    file.write_all(&encode_snapshot(total_blocks, window))?;
+    file.sync_all()?;
+    fs::rename(&temp_path, &path)?;
+    sync_dir(dir)
+}
+
+/// Make a rename in the `dir`ectory durable. Only possible on unix platforms.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut checksum = Crc32::new();
+    checksum.update(bytes);
+    checksum.finish()
+}
+
+fn encode_header<B: FixedWidthBlock>(magic: [u8; 4], window_size: usize) -> [u8; HEADER_LEN] {
+    let mut bytes = [0; HEADER_LEN];
+    bytes[0..4].copy_from_slice(&magic);
+    bytes[4] = VERSION;
+    bytes[5] = binary::width::<B>();
+    bytes[6] = if B::SIGNED { binary::FLAG_SIGNED } else { 0 };
+    bytes[8..16].copy_from_slice(&(window_size as u64).to_le_bytes());
+    bytes
+}
+
+fn check_header<const VALIDATION_WINDOW_SIZE: usize, B: FixedWidthBlock>(
+    bytes: &[u8],
+    magic: [u8; 4],
+    file: &'static str,
+) -> Result<(), WalError<VALIDATION_WINDOW_SIZE, B>> {
+    let invalid = |reason| Err(WalError::Invalid { file, reason });
+
+    if bytes.len() < HEADER_LEN {
+        return invalid("the header is truncated");
+    }
+    let expected = encode_header::<B>(magic, VALIDATION_WINDOW_SIZE);
+    if bytes[0..4] != magic {
+        invalid("not a write-ahead log file")
+    } else if bytes[4] != VERSION {
+        invalid("unsupported version")
+    } else if bytes[5..7] != expected[5..7] {
+        invalid("the blocks are of a different type")
+    } else if bytes[8..16] != expected[8..16] {
+        invalid("the validation window size is different")
+    } else {
+        Ok(())
+    }
+}
+
+fn record_len<B: FixedWidthBlock>() -> usize {
+    8 + usize::from(binary::width::<B>()) + 4
+}
+
+fn encode_record<B: FixedWidthBlock>(block_number: u64, block: B) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(record_len::<B>());
+    bytes.extend_from_slice(&block_number.to_le_bytes());
+    bytes.extend_from_slice(block.to_le_bytes().as_ref());
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+    bytes
+}
+
+/// `None` if the record is truncated or its checksum doesn't match.
+fn decode_record<B: FixedWidthBlock>(record: &[u8]) -> Option<(u64, B)> {
+    if record.len() != record_len::<B>() {
+        return None;
+    }
+
+    let (content, expected) = record.split_at(record.len() - 4);
+    if expected != checksum(content).to_le_bytes() {
+        return None;
+    }
+
+    let (block_number, block) = content.split_at(8);
+    let block_number = u64::from_le_bytes(block_number.try_into().unwrap());
+    Some((block_number, decode_block(block)))
+}
+
+fn decode_block<B: FixedWidthBlock>(bytes: &[u8]) -> B {
+    let mut block = B::Bytes::default();
+    block.as_mut().copy_from_slice(bytes);
+    B::from_le_bytes(block)
+}
+
+fn encode_snapshot<B: FixedWidthBlock>(
+    total_blocks: u64,
+    window: impl ExactSizeIterator<Item = B>,
+) -> Vec<u8> {
+    let mut bytes = encode_header::<B>(SNAPSHOT_MAGIC, window.len()).to_vec();
+    bytes.extend_from_slice(&total_blocks.to_le_bytes());
+    for block in window {
+        bytes.extend_from_slice(block.to_le_bytes().as_ref());
+    }
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+    bytes
+}
+
+/// Number of blocks added to the mine, and its validation window.
+fn decode_snapshot<const VALIDATION_WINDOW_SIZE: usize, B: FixedWidthBlock>(
+    bytes: &[u8],
+) -> Result<(u64, [B; VALIDATION_WINDOW_SIZE]), WalError<VALIDATION_WINDOW_SIZE, B>> {
+    let invalid = |reason| WalError::Invalid {
+        file: SNAPSHOT_FILE,
+        reason,
+    };
+
+    check_header::<VALIDATION_WINDOW_SIZE, B>(bytes, SNAPSHOT_MAGIC, SNAPSHOT_FILE)?;
+    let width = usize::from(binary::width::<B>());
+    if bytes.len() != HEADER_LEN + 8 + VALIDATION_WINDOW_SIZE * width + 4 {
+        return Err(invalid("the snapshot is truncated"));
+    }
+
+    let (content, expected) = bytes.split_at(bytes.len() - 4);
+    if expected != checksum(content).to_le_bytes() {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let (total_blocks, window) = content[HEADER_LEN..].split_at(8);
+    let total_blocks = u64::from_le_bytes(total_blocks.try_into().unwrap());
+    if total_blocks < VALIDATION_WINDOW_SIZE as u64 {
+        return Err(invalid("invalid number of blocks"));
+    }
+
+    let window = window
+        .chunks(width)
+        .map(decode_block)
+        .collect::<Vec<B>>()
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("the length is checked"));
+
+    Ok((total_blocks, window))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::{
+        hash_mine::HashMine,
+        test_data::{EXAMPLE, FIRST_INVALID_BLOCK_NUMBER},
+        two_ptr_mine::TwoPtrMine,
+    };
+
+    type Wal = WalMine<5, u64, HashMine<5, u64>>;
+
+    fn initialization_blocks() -> [u64; 5] {
+        EXAMPLE[..5].try_into().unwrap()
+    }
+
+    #[test]
+    fn reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wal = Wal::create(dir.path(), initialization_blocks())
+            .unwrap()
+            .with_snapshot_interval(4);
+        assert_eq!(wal.try_extend(EXAMPLE[5..14].iter().copied()), Ok(()));
+        assert_eq!(
+            wal.try_extend_one(127),
+            Err(WalError::Mine(MineError::InvalidBlock(
+                127,
+                FIRST_INVALID_BLOCK_NUMBER
+            )))
+        );
+        drop(wal);
+
+        assert_eq!(
+            Wal::create(dir.path(), initialization_blocks()).err(),
+            Some(WalError::Io(io::ErrorKind::AlreadyExists))
+        );
+
+        // snapshots after blocks 9 and 13, block 14 is in the log
+        let mut wal = Wal::open(dir.path()).unwrap();
+        assert_eq!(
+            wal.recovery(),
+            Recovery {
+                replayed_blocks: 1,
+                discarded_bytes: 0
+            }
+        );
+        assert_eq!(wal.total_blocks(), 14);
+        assert!(wal.mine().validation_blocks().eq(&[95, 102, 117, 150, 182]));
+        assert_eq!(
+            wal.try_extend_one(127),
+            Err(WalError::Mine(MineError::InvalidBlock(
+                127,
+                FIRST_INVALID_BLOCK_NUMBER
+            ))),
+            "Block numbers must survive restarts"
+        );
+        assert_eq!(wal.try_extend_one(150 + 182), Ok(()));
+        drop(wal);
+
+        let wal = Wal::open(dir.path()).unwrap();
+        assert_eq!(wal.total_blocks(), 15);
+        assert_eq!(wal.recovery().replayed_blocks, 2);
+    }
+
+    #[test]
+    fn interrupted_create() {
+        let dir = tempfile::tempdir().unwrap();
+        Wal::create(dir.path(), initialization_blocks()).unwrap();
+
+        // a crash in `create` of an earlier version, before the first snapshot
+        fs::remove_file(dir.path().join(SNAPSHOT_FILE)).unwrap();
+        assert_eq!(
+            Wal::open(dir.path()).err(),
+            Some(WalError::Io(io::ErrorKind::NotFound))
+        );
+        let mut wal = Wal::create(dir.path(), initialization_blocks()).unwrap();
+        assert_eq!(wal.try_extend(EXAMPLE[5..14].iter().copied()), Ok(()));
+        drop(wal);
+
+        // a crash in `create` after the first snapshot, before the log
+        let dir = tempfile::tempdir().unwrap();
+        Wal::create(dir.path(), initialization_blocks()).unwrap();
+        fs::remove_file(dir.path().join(LOG_FILE)).unwrap();
+        Wal::create(dir.path(), initialization_blocks()).unwrap();
+        assert_eq!(Wal::open(dir.path()).unwrap().total_blocks(), 5);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn failed_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wal = Wal::create(dir.path(), initialization_blocks()).unwrap();
+        assert_eq!(wal.try_extend(EXAMPLE[5..8].iter().copied()), Ok(()));
+
+        // every write fails with "no space left on device"
+        wal.log = OpenOptions::new().write(true).open("/dev/full").unwrap();
+        assert_eq!(
+            wal.try_extend_one(EXAMPLE[8]),
+            Err(WalError::Io(io::ErrorKind::StorageFull))
+        );
+        assert_eq!(
+            wal.total_blocks(),
+            8,
+            "Unlogged blocks stay out of the mine"
+        );
+        assert_eq!(wal.try_extend_one(EXAMPLE[8]), Err(WalError::Poisoned));
+        assert_eq!(wal.snapshot(), Err(WalError::Poisoned));
+        drop(wal);
+
+        let mut wal = Wal::open(dir.path()).unwrap();
+        assert_eq!(wal.total_blocks(), 8);
+        assert_eq!(wal.try_extend(EXAMPLE[8..14].iter().copied()), Ok(()));
+    }
+
+    #[test]
+    fn torn_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wal =
+            WalMine::<5, u64, TwoPtrMine<5, u64>>::create(dir.path(), initialization_blocks())
+                .unwrap();
+        wal.try_extend(EXAMPLE[5..14].iter().copied()).unwrap();
+        drop(wal);
+
+        let snapshot = fs::read(dir.path().join(SNAPSHOT_FILE)).unwrap();
+        let log = fs::read(dir.path().join(LOG_FILE)).unwrap();
+        let record_len = record_len::<u64>();
+        assert_eq!(log.len(), HEADER_LEN + 9 * record_len);
+
+        for len in 0..=log.len() {
+            // a crash either truncates the last record, or leaves garbage in its place
+            let garbage_len = if len < HEADER_LEN {
+                0
+            } else {
+                (len - HEADER_LEN).next_multiple_of(record_len) - (len - HEADER_LEN)
+            };
+            let garbage = vec![0xa5; garbage_len];
+            for torn_log in [log[..len].to_vec(), [&log[..len], &garbage[..]].concat()] {
+                let dir = tempfile::tempdir().unwrap();
+                fs::write(dir.path().join(SNAPSHOT_FILE), &snapshot).unwrap();
+                fs::write(dir.path().join(LOG_FILE), &torn_log).unwrap();
+
+                let wal = WalMine::<5, u64, TwoPtrMine<5, u64>>::open(dir.path());
+                if len < HEADER_LEN {
+                    assert_eq!(
+                        wal.err(),
+                        Some(WalError::Invalid {
+                            file: LOG_FILE,
+                            reason: "the header is truncated"
+                        })
+                    );
+                    continue;
+                }
+
+                let mut wal = wal.unwrap();
+                let records = (len - HEADER_LEN) / record_len;
+                let valid_len = HEADER_LEN + records * record_len;
+                assert_eq!(wal.total_blocks(), 5 + records as u64);
+                assert_eq!(
+                    wal.recovery(),
+                    Recovery {
+                        replayed_blocks: records as u64,
+                        discarded_bytes: (torn_log.len() - valid_len) as u64
+                    }
+                );
+                assert!(wal
+                    .mine()
+                    .validation_blocks()
+                    .eq(&EXAMPLE[records..records + 5]));
+                assert_eq!(
+                    fs::metadata(dir.path().join(LOG_FILE)).unwrap().len(),
+                    valid_len as u64,
+                    "Torn records are cut from the log"
+                );
+
+                // validation continues after the last logged block
+                assert_eq!(
+                    wal.try_extend(EXAMPLE[5 + records..14].iter().copied()),
+                    Ok(())
+                );
+                drop(wal);
+                let wal = WalMine::<5, u64, TwoPtrMine<5, u64>>::open(dir.path()).unwrap();
+                assert_eq!(wal.total_blocks(), 14);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        Wal::create(dir.path(), initialization_blocks()).unwrap();
+        let snapshot = fs::read(dir.path().join(SNAPSHOT_FILE)).unwrap();
+
+        assert_eq!(
+            WalMine::<5, u32, HashMine<5, u32>>::open(dir.path()).err(),
+            Some(WalError::Invalid {
+                file: SNAPSHOT_FILE,
+                reason: "the blocks are of a different type"
+            })
+        );
+        assert_eq!(
+            WalMine::<4, u64, HashMine<4, u64>>::open(dir.path()).err(),
+            Some(WalError::Invalid {
+                file: SNAPSHOT_FILE,
+                reason: "the validation window size is different"
+            })
+        );
+
+        for len in 0..snapshot.len() {
+            fs::write(dir.path().join(SNAPSHOT_FILE), &snapshot[..len]).unwrap();
+            assert!(matches!(
+                Wal::open(dir.path()),
+                Err(WalError::Invalid {
+                    file: SNAPSHOT_FILE,
+                    ..
+                })
+            ));
+        }
+
+        let mut corrupted = snapshot.clone();
+        corrupted[HEADER_LEN + 8] ^= 1;
+        fs::write(dir.path().join(SNAPSHOT_FILE), &corrupted).unwrap();
+        assert_eq!(
+            Wal::open(dir.path()).err(),
+            Some(WalError::Invalid {
+                file: SNAPSHOT_FILE,
+                reason: "checksum mismatch"
+            })
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{
+    adaptive_mine::{AdaptiveMine, Strategy},
+    mine::{Block, Mine, MineError},
+};
+
+use std::{collections::VecDeque, hash::Hash, mem, ops::Add};
+
+/// Concrete implementation of [Mine] validating `Wide` blocks, while storing them as
+/// `Narrow` blocks for as long as they fit.
+///
+/// A block fits if twice its value fits in `Narrow`, so no sum of two blocks in the
+/// window can overflow. The first valid block that doesn't fit migrates the mine to `Wide`
+/// blocks for good, carrying over the validation window and the block count.
+/// The results are the same as validating with `Wide` blocks from the start.
+///
+/// Backed by an [AdaptiveMine] of either block type, keeping its [Strategy].
+/// # Performance
+/// While narrow, the validation window is also kept in `Wide` blocks, to hand out
+/// references to it. The migration costs as much as creating a new mine.
+#[derive(Clone, Debug)]
+pub struct WideningMine<const VALIDATION_WINDOW_SIZE: usize, Narrow, Wide>
+where
+    Narrow: Block + Hash + Clone + Ord,
+    Wide: Block + Hash + Clone + Ord,
+{
+    storage: Storage<VALIDATION_WINDOW_SIZE, Narrow, Wide>,
+}
+
+#[derive(Clone, Debug)]
+enum Storage<const VALIDATION_WINDOW_SIZE: usize, Narrow, Wide>
+where
+    Narrow: Block + Hash + Clone + Ord,
+    Wide: Block + Hash + Clone + Ord,
+{
+    Narrow {
+        mine: AdaptiveMine<VALIDATION_WINDOW_SIZE, Narrow>,
+        /// Same blocks as the validation window of the `mine`, in arrival order.
+        window: VecDeque<Wide>,
+    },
+    Wide(AdaptiveMine<VALIDATION_WINDOW_SIZE, Wide>),
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, Narrow, Wide>
+    WideningMine<VALIDATION_WINDOW_SIZE, Narrow, Wide>
+where
+    Narrow: Block + Hash + Clone + Ord + TryFrom<Wide> + CheckedAdd,
+    Wide: Block + Hash + Clone + Ord + From<Narrow>,
+    for<'a> &'a Narrow: Add<&'a Narrow, Output = Narrow>,
+    for<'a> &'a Wide: Add<&'a Wide, Output = Wide>,
+{
+    /// Create a new mine with given `initialization_blocks` backed by the given `strategy`.
+    /// Starts with `Wide` blocks if any of the initialization blocks doesn't fit.
+    /// No validation is performed on the initialization blocks.
+    pub fn with_strategy(
+        initialization_blocks: [Wide; VALIDATION_WINDOW_SIZE],
+        strategy: Strategy,
+    ) -> Self {
+        Self::from_window(
+            initialization_blocks,
+            VALIDATION_WINDOW_SIZE as u64,
+            strategy,
+        )
+    }
+
+    fn from_window(
+        validation_blocks: [Wide; VALIDATION_WINDOW_SIZE],
+        total_blocks: u64,
+        strategy: Strategy,
+    ) -> Self {
+        let narrow_blocks = validation_blocks
+            .iter()
+            .map(narrow)
+            .collect::<Option<VecDeque<Narrow>>>();
+        let validation_blocks = VecDeque::from(validation_blocks);
+
+        let storage = match narrow_blocks {
+            Some(narrow_blocks) => Storage::Narrow {
+                mine: AdaptiveMine::from_window(narrow_blocks, total_blocks, strategy),
+                window: validation_blocks,
+            },
+            None => Storage::Wide(AdaptiveMine::from_window(
+                validation_blocks,
+                total_blocks,
+                strategy,
+            )),
+        };
+
+        Self { storage }
+    }
+
+    /// Whether the mine has migrated to `Wide` blocks.
+    pub fn is_widened(&self) -> bool {
+        matches!(self.storage, Storage::Wide(_))
+    }
+
+    /// Strategy currently backing the mine.
+    pub fn strategy(&self) -> Strategy {
+        match &self.storage {
+            Storage::Narrow { mine, .. } => mine.strategy(),
+            Storage::Wide(mine) => mine.strategy(),
+        }
+    }
+
+    /// Migrate the mine to `Wide` blocks. Widening a widened mine is a no-op.
+    fn widen(&mut self) {
+        if let Storage::Narrow { mine, window } = &mut self.storage {
+            let mine =
+                AdaptiveMine::from_window(mem::take(window), mine.total_blocks(), mine.strategy());
+            self.storage = Storage::Wide(mine);
+        }
+    }
+}
+
+impl<const VALIDATION_WINDOW_SIZE: usize, Narrow, Wide> Mine<VALIDATION_WINDOW_SIZE, Wide>
+    for WideningMine<VALIDATION_WINDOW_SIZE, Narrow, Wide>
+where
+    Narrow: Block + Hash + Clone + Ord + TryFrom<Wide> + CheckedAdd,
+    Wide: Block + Hash + Clone + Ord + From<Narrow>,
+    for<'a> &'a Narrow: Add<&'a Narrow, Output = Narrow>,
+    for<'a> &'a Wide: Add<&'a Wide, Output = Wide>,
+{
+    type Error = MineError<VALIDATION_WINDOW_SIZE, Wide>;
+
+    /// Recreate a mine from its `validation_blocks`, see [Mine::resume].
+    /// No validation is performed on the validation blocks.
+    /// See [AdaptiveMine::resume] for the choice of the strategy.
+    fn resume(validation_blocks: [Wide; VALIDATION_WINDOW_SIZE], total_blocks: u64) -> Self {
+        let strategy = Strategy::choose::<VALIDATION_WINDOW_SIZE, Narrow>(None);
+        Self::from_window(validation_blocks, total_blocks, strategy)
+    }
+
+    fn try_extend_one(&mut self, new_block: Wide) -> Result<(), Self::Error> {
+        if let Storage::Narrow { mine, window } = &mut self.storage {
+            if let Some(narrow_block) = narrow(&new_block) {
+                mine.try_extend_one(narrow_block).map_err(widen_error)?;
+                window.pop_front();
+                window.push_back(new_block);
+                return Ok(());
+            }
+
+            // only a valid block widens the mine
+            if find_pair(window, &new_block).is_none() {
+                return Err(MineError::InvalidBlock(new_block, mine.total_blocks() + 1));
+            }
+            self.widen();
+        }
+
+        match &mut self.storage {
+            Storage::Wide(mine) => mine.try_extend_one(new_block),
+            Storage::Narrow { .. } => unreachable!("the mine is widened"),
+        }
+    }
+
+    fn peek(&self, new_block: &Wide) -> Result<(), Self::Error> {
+        match &self.storage {
+            Storage::Narrow { mine, window } => match narrow(new_block) {
+                Some(narrow_block) => mine.peek(&narrow_block).map_err(widen_error),
+                None => match find_pair(window, new_block) {
+                    Some(_) => Ok(()),
+                    None => Err(MineError::InvalidBlock(
+                        new_block.clone(),
+                        mine.total_blocks() + 1,
+                    )),
+                },
+            },
+            Storage::Wide(mine) => mine.peek(new_block),
+        }
+    }
+
+    fn validation_blocks<'a>(&'a self) -> impl ExactSizeIterator<Item = &'a Wide>
+    where
+        Wide: 'a,
+    {
+        match &self.storage {
+            Storage::Narrow { window, .. } => window.iter(),
+            Storage::Wide(AdaptiveMine::Hash(mine)) => mine.validation_blocks.iter(),
+            Storage::Wide(AdaptiveMine::TwoPtr(mine)) => mine.validation_blocks.iter(),
+        }
+    }
+
+    fn total_blocks(&self) -> u64 {
+        match &self.storage {
+            Storage::Narrow { mine, .. } => mine.total_blocks(),
+            Storage::Wide(mine) => mine.total_blocks(),
+        }
+    }
+
+    /// `None` until the mine is widened.
+    fn ordered_validation_blocks(&self) -> Option<&[Wide]> {
+        match &self.storage {
+            Storage::Narrow { .. } => None,
+            Storage::Wide(mine) => mine.ordered_validation_blocks(),
+        }
+    }
+
+    fn distinct_pair_sums(&self) -> Option<usize> {
+        match &self.storage {
+            Storage::Narrow { mine, .. } => mine.distinct_pair_sums(),
+            Storage::Wide(mine) => mine.distinct_pair_sums(),
+        }
+    }
+
+    fn justifying_pair(&self, new_block: &Wide) -> Option<(&Wide, &Wide)> {
+        match &self.storage {
+            Storage::Narrow { mine, window } => match narrow(new_block) {
+                Some(narrow_block) => {
+                    let (first, second) = mine.justifying_pair(&narrow_block)?;
+                    let (first, second) = (Wide::from(first.clone()), Wide::from(second.clone()));
+
+                    let i = window.iter().position(|block| *block == first)?;
+                    let j = (0..window.len()).find(|&j| j != i && window[j] == second)?;
+                    Some((&window[i], &window[j]))
+                }
+                // same as the wide mine would answer, HashMine can't tell the pair
+                None => match mine.strategy() {
+                    Strategy::Hash => None,
+                    Strategy::TwoPtr => find_pair(window, new_block)
+                        .map(|(first, second)| (first.min(second), first.max(second))),
+                },
+            },
+            Storage::Wide(mine) => mine.justifying_pair(new_block),
+        }
+    }
+}
+
+/// `block` as a `Narrow` block, if twice its value fits in `Narrow` too.
+/// The sum of any two such blocks lies in between, so it fits as well.
+fn narrow<Narrow, Wide>(block: &Wide) -> Option<Narrow>
+where
+    Narrow: TryFrom<Wide> + CheckedAdd,
+    Wide: Clone,
+{
+    let narrow = Narrow::try_from(block.clone()).ok()?;
+    narrow.checked_add(&narrow)?;
+    Some(narrow)
+}
+
+/// Addition that reports overflow, for the `Narrow` blocks of a [WideningMine].
+/// Implemented for the primitive integers, and [BigBlock](crate::mine::BigBlock)
+/// with the `bigint` feature.
+pub trait CheckedAdd: Sized {
+    /// `self + other`, `None` if it overflows.
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+}
+
+macro_rules! checked_add {
+    ($($block:ty),*) => {
+        $(
+            impl CheckedAdd for $block {
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$block>::checked_add(*self, *other)
+                }
+            }
+        )*
+    };
+}
+
+checked_add!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+#[cfg(feature = "bigint")]
+impl CheckedAdd for crate::mine::BigBlock {
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+fn widen_error<const VALIDATION_WINDOW_SIZE: usize, Narrow, Wide>(
+    error: MineError<VALIDATION_WINDOW_SIZE, Narrow>,
+) -> MineError<VALIDATION_WINDOW_SIZE, Wide>
+where
+    Narrow: Block,
+    Wide: Block + From<Narrow>,
+{
+    match error {
+        MineError::InvalidInitializationSequenceLen => MineError::InvalidInitializationSequenceLen,
+        MineError::InvalidBlock(block, block_number) => {
+            MineError::InvalidBlock(block.into(), block_number)
+        }
+    }
+}
+
+/// Two blocks at different positions in the `window` summing up to `target`.
+/// Quadratic, only used for blocks that don't fit the narrow mine.
+fn find_pair<'a, B>(window: &'a VecDeque<B>, target: &B) -> Option<(&'a B, &'a B)>
+where
+    B: Eq,
+    for<'b> &'b B: Add<&'b B, Output = B>,
+{
+    window.iter().enumerate().find_map(|(i, first)| {
+        window
+            .iter()
+            .skip(i + 1)
+            .find(|second| first + *second == *target)
+            .map(|second| (first, second))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Fibonacci like stream growing past `u64::MAX`, with an invalid block every few blocks.
+    fn stream() -> impl Iterator<Item = u128> {
+        let mut last = (4, 5);
+        (0..150).map(move |i| {
+            if i % 7 == 6 {
+                last.1 + 1
+            } else {
+                last = (last.1, last.0 + last.1);
+                last.1
+            }
+        })
+    }
+
+    #[test]
+    fn same_as_wide_mine() {
+        for strategy in [Strategy::Hash, Strategy::TwoPtr] {
+            let initialization_blocks = [1, 2, 3, 4, 5];
+            let mut mine =
+                WideningMine::<5, u64, u128>::with_strategy(initialization_blocks, strategy);
+            let mut wide_mine =
+                AdaptiveMine::<5, u128>::with_strategy(initialization_blocks, strategy);
+
+            let mut widened_at = None;
+            for block in stream() {
+                assert_eq!(mine.peek(&block), wide_mine.peek(&block));
+                assert_eq!(
+                    mine.justifying_pair(&block),
+                    wide_mine.justifying_pair(&block)
+                );
+                assert_eq!(mine.try_extend_one(block), wide_mine.try_extend_one(block));
+                assert!(mine.validation_blocks().eq(wide_mine.validation_blocks()));
+                assert_eq!(mine.total_blocks(), wide_mine.total_blocks());
+                assert_eq!(mine.distinct_pair_sums(), wide_mine.distinct_pair_sums());
+                assert_eq!(mine.strategy(), strategy);
+
+                if mine.is_widened() && widened_at.is_none() {
+                    widened_at = Some(block);
+                }
+            }
+
+            let widened_at = widened_at.unwrap();
+            assert!(widened_at > u64::MAX as u128 / 2);
+            assert!(widened_at <= u64::MAX as u128);
+            assert_eq!(
+                mine.ordered_validation_blocks(),
+                wide_mine.ordered_validation_blocks()
+            );
+        }
+    }
+
+    #[test]
+    fn widens_on_first_block_that_does_not_fit() {
+        let mut mine = WideningMine::<2, u8, u16>::new([1, 1]);
+        assert_eq!(mine.try_extend([2, 3, 5, 8, 13, 21, 34, 55, 89]), Ok(()));
+        assert!(!mine.is_widened());
+        assert_eq!(mine.ordered_validation_blocks(), None);
+
+        assert_eq!(mine.peek(&144), Ok(()));
+        assert_eq!(mine.justifying_pair(&144), Some((&55, &89)));
+        assert!(!mine.is_widened(), "Peeking doesn't widen the mine");
+
+        assert_eq!(mine.try_extend_one(144), Ok(()));
+        assert!(mine.is_widened());
+        assert_eq!(
+            mine.try_extend_one(234),
+            Err(MineError::InvalidBlock(234, 13))
+        );
+        assert_eq!(mine.try_extend_one(233), Ok(()));
+        assert_eq!(mine.ordered_validation_blocks(), Some(&[144, 233][..]));
+    }
+
+    #[test]
+    fn wide_initialization_blocks() {
+        let mine = WideningMine::<3, u8, u16>::new([1, 2, 200]);
+        assert!(mine.is_widened());
+
+        let mut mine = WideningMine::<3, i8, i16>::new([-60, 1, 2]);
+        assert!(!mine.is_widened());
+        assert_eq!(mine.try_extend_one(-59), Ok(()));
+        assert_eq!(
+            mine.try_extend_one(-70),
+            Err(MineError::InvalidBlock(-70, 5))
+        );
+        assert!(!mine.is_widened(), "Invalid blocks don't widen the mine");
+        assert_eq!(mine.try_extend_one(-57), Ok(()));
+        assert!(mine.validation_blocks().eq(&[2, -59, -57]));
+        assert_eq!(mine.try_extend_one(-116), Ok(()));
+        assert!(mine.is_widened());
+    }
+
+    #[test]
+    fn no_headroom_in_wide_blocks() {
+        // doubling these overflows `Wide`
+        let mut mine = WideningMine::<2, u64, u64>::new([u64::MAX - 1, 1]);
+        assert!(mine.is_widened());
+        assert_eq!(mine.try_extend_one(u64::MAX), Ok(()));
+
+        let mut mine = WideningMine::<2, u8, u8>::new([1, 2]);
+        assert!(!mine.is_widened());
+        assert_eq!(mine.try_extend_one(3), Ok(()));
+        assert_eq!(
+            mine.try_extend_one(200),
+            Err(MineError::InvalidBlock(200, 4))
+        );
+        assert!(!mine.is_widened());
+    }
+}